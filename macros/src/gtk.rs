@@ -8,12 +8,39 @@ fn to_string_literal<S: ToString>(s: S) -> Literal {
     Literal::string(&s.to_string())
 }
 
+/// Test whether an attribute is the `key` attribute, which is consumed by the
+/// differ rather than being set on the object or component.
+fn is_key(attribute: &Attribute) -> bool {
+    match attribute {
+        Attribute::Property {
+            child: false,
+            parent,
+            name,
+            ..
+        } => parent.is_empty() && name == "key",
+        _ => false,
+    }
+}
+
+fn expand_key(attribute: &Attribute) -> TokenStream {
+    match attribute {
+        Attribute::Property { value, .. } => {
+            let value = to_stream(value);
+            quote!(
+                key = Some(std::string::ToString::to_string(&(#value)));
+            )
+        }
+        _ => unreachable!(),
+    }
+}
+
 fn count_attributes(attributes: &[Attribute]) -> (usize, usize, usize) {
     let mut props = 0;
     let mut child_props = 0;
     let mut handlers = 0;
     for attribute in attributes {
         match attribute {
+            Attribute::Property { .. } if is_key(attribute) => {}
             Attribute::Property { child, .. } => {
                 if *child {
                     child_props += 1
@@ -29,6 +56,7 @@ fn count_attributes(attributes: &[Attribute]) -> (usize, usize, usize) {
 
 pub fn expand_gtk(gtk: &GtkElement) -> TokenStream {
     match gtk {
+        GtkElement::Widget(widget) if is_memo(widget) => expand_memo(widget),
         GtkElement::Widget(widget) => expand_widget(widget),
         GtkElement::Component(component) => expand_component(component),
         GtkElement::Block(_block) => panic!("blocks not allowed in this position"),
@@ -41,8 +69,13 @@ pub fn expand_component(gtk: &GtkComponent) -> TokenStream {
         use vgtk::{Component, vnode::VComponent, vnode::PropTransform};
         let mut vcomp = VComponent::new::<#name>();
         let mut props = <#name as Component>::Properties::default();
+        let mut key = None;
     );
     for attribute in &gtk.attributes {
+        if is_key(attribute) {
+            out.extend(expand_key(attribute));
+            continue;
+        }
         out.extend(match attribute {
             Attribute::Property {
                 child,
@@ -90,11 +123,60 @@ pub fn expand_component(gtk: &GtkComponent) -> TokenStream {
     }
     quote!({
         #out
+        vcomp.key = key;
         vcomp.set_props::<#name>(props);
         VNode::Component(vcomp)
     })
 }
 
+/// Test whether an element is a `<memo>` element rather than an object.
+fn is_memo(gtk: &GtkWidget) -> bool {
+    match &gtk.name[..] {
+        [Token::Ident(name)] => name == "memo",
+        _ => false,
+    }
+}
+
+pub fn expand_memo(gtk: &GtkWidget) -> TokenStream {
+    let span = gtk.name[0].span();
+    let mut deps = None;
+    for attribute in &gtk.attributes {
+        match attribute {
+            Attribute::Property {
+                child: false,
+                parent,
+                name,
+                value,
+            } if parent.is_empty() && name == "deps" => deps = Some(to_stream(value)),
+            _ => {
+                return quote_spanned! {span =>
+                    compile_error! { "memo only takes a deps attribute" }
+                }
+            }
+        }
+    }
+    let deps = match deps {
+        Some(deps) => deps,
+        None => {
+            return quote_spanned! {span =>
+                compile_error! { "memo needs a deps attribute" }
+            }
+        }
+    };
+    let child = match &gtk.children[..] {
+        [child] if is_block(child).is_none() => expand_gtk(child),
+        _ => {
+            return quote_spanned! {span =>
+                compile_error! { "memo must have exactly one child element" }
+            }
+        }
+    };
+    quote!({
+        let deps = #deps;
+        vgtk::vnode::VNode::Memo(vgtk::vnode::VMemo::new(deps, #child))
+    })
+}
+
 fn is_block(gtk: &GtkElement) -> Option<&Group> {
     match gtk {
         GtkElement::Block(block) => Some(block),
@@ -115,6 +197,7 @@ pub fn expand_widget(gtk: &GtkWidget) -> TokenStream {
         let mut child_props = Vec::with_capacity(#child_prop_count);
         let mut handlers = Vec::with_capacity(#handler_count);
         let mut children = Vec::new();
+        let mut key = None;
     );
     if !gtk.constructor.is_empty() {
         let cons = to_stream(&gtk.constructor);
@@ -129,6 +212,10 @@ pub fn expand_widget(gtk: &GtkWidget) -> TokenStream {
         ));
     }
     for attribute in &gtk.attributes {
+        if is_key(attribute) {
+            out.extend(expand_key(attribute));
+            continue;
+        }
         out.extend(match attribute {
             Attribute::Property {
                 child,
@@ -176,6 +263,7 @@ pub fn expand_widget(gtk: &GtkWidget) -> TokenStream {
             child_props,
             handlers,
             children,
+            key,
         })
     })
 }
@@ -247,7 +335,10 @@ pub fn expand_property(
             let value = #value;
             VProperty {
                 name: #prop_name,
-                set: std::boxed::Box::new(move |object: &vgtk::lib::glib::Object, parent: Option<&vgtk::lib::glib::Object>, force: bool| {
+                value: value.cached(),
+                equal: value.cache_equal(),
+                live: value.is_live(),
+                set: std::rc::Rc::new(move |object: &vgtk::lib::glib::Object, parent: Option<&vgtk::lib::glib::Object>, force: bool| {
                     #setter_prelude
                     #setter_body
                 }),
@@ -276,7 +367,7 @@ pub fn expand_handler(
             vgtk::lib::glib::MainContext::ref_thread_default().spawn_local(
                 async move {
                     let msg = async move { #body_s }.await;
-                    let ret = msg.into_signal_return();
+                    let ret = vgtk::ext::IntoSignalReturn::into_signal_return(&msg);
                     scope.send_message(msg);
                     ret
                 }
@@ -285,7 +376,7 @@ pub fn expand_handler(
     } else {
         quote!({
             let msg = { #body_s };
-            let ret = msg.into_signal_return();
+            let ret = vgtk::ext::IntoSignalReturn::into_signal_return(&msg);
             scope.send_message(msg);
             ret
         })
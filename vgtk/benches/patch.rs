@@ -0,0 +1,111 @@
+//! Time re-rendering a large widget tree in which only one label changes.
+//!
+//! Each render is timed twice: first with properties compared with the values
+//! set by the previous render, then with every property wrapped in `live()`,
+//! which compares them with the widgets' current values the way every property
+//! used to be compared, as a baseline.
+//!
+//! Run it with `cargo bench -p vgtk --bench patch`. It needs a display to open
+//! a window on.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use vgtk::ext::*;
+use vgtk::lib::gio::{prelude::*, ApplicationFlags};
+use vgtk::lib::glib::MainContext;
+use vgtk::lib::gtk::{prelude::*, Application, Box, Label, Orientation, ScrolledWindow, Window};
+use vgtk::properties::live;
+use vgtk::{gtk, Component, Scope, UpdateAction, VNode};
+
+const ROWS: usize = 5000;
+const RENDERS: u32 = 100;
+
+// Whether to render every property as `live()`.
+static LIVE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Debug)]
+enum Message {
+    Tick,
+}
+
+#[derive(Default)]
+struct Dashboard {
+    ticks: usize,
+}
+
+fn row(index: usize) -> VNode<Dashboard> {
+    let label = format!("Row {}", index);
+    if LIVE.load(Ordering::SeqCst) {
+        gtk! { <Label label=live(label) selectable=live(true) xalign=live(0.0) /> }
+    } else {
+        gtk! { <Label label=label selectable=true xalign=0.0 /> }
+    }
+}
+
+impl Component for Dashboard {
+    type Message = Message;
+    type Properties = ();
+
+    fn update(&mut self, message: Message) -> UpdateAction<Self> {
+        match message {
+            Message::Tick => {
+                self.ticks += 1;
+                UpdateAction::Render
+            }
+        }
+    }
+
+    fn view(&self) -> VNode<Self> {
+        gtk! {
+            <Application::new_unwrap(Some("rs.vgtk.bench.patch"), ApplicationFlags::empty())>
+                <Window default_width=400 default_height=600>
+                    <ScrolledWindow>
+                        <Box::new(Orientation::Vertical, 0)>
+                            <Label label=format!("Ticks: {}", self.ticks) />
+                            { (0..ROWS).map(row) }
+                        </Box>
+                    </ScrolledWindow>
+                </Window>
+            </Application>
+        }
+    }
+}
+
+fn settle() {
+    let context = MainContext::default();
+    while context.iteration(false) {}
+}
+
+fn time_renders(scope: &Scope<Dashboard>) -> Duration {
+    let started = Instant::now();
+    for _ in 0..RENDERS {
+        scope.send_message(Message::Tick);
+        settle();
+    }
+    started.elapsed() / RENDERS
+}
+
+fn main() {
+    let (app, scope) = vgtk::start::<Dashboard>();
+    app.activate();
+    settle();
+    let cached = time_renders(&scope);
+
+    // Switch to live properties, and let one render pass before timing.
+    LIVE.store(true, Ordering::SeqCst);
+    scope.send_message(Message::Tick);
+    settle();
+    let live = time_renders(&scope);
+
+    println!("{} renders of {} rows:", RENDERS, ROWS);
+    println!(
+        "  compared with the previous render: {:?} per render",
+        cached
+    );
+    println!("  compared with the widgets (live):  {:?} per render", live);
+    println!(
+        "  speedup: {:.1}x",
+        live.as_secs_f64() / cached.as_secs_f64()
+    );
+}
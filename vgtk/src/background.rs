@@ -0,0 +1,111 @@
+use futures::channel::mpsc::{channel, Receiver, Sender};
+use futures::executor::block_on;
+use futures::SinkExt;
+use glib::ThreadPool;
+
+use std::fmt::{Debug, Error, Formatter};
+use std::sync::Mutex;
+
+// How many progress messages a job can get ahead of the main loop before
+// `Progress::send()` waits for it to catch up.
+const PROGRESS_BOUND: usize = 16;
+
+thread_local! {
+    // One thread per processor: the jobs are meant to be CPU bound, so more
+    // threads than that would only make them take turns. Jobs beyond that
+    // wait in the pool's queue.
+    static POOL: ThreadPool = ThreadPool::new_shared(Some(glib::get_num_processors()))
+        .expect("unable to create the background thread pool");
+}
+
+/// A handle for a background job to send progress messages through.
+///
+/// A job started with
+/// [`UpdateAction::background_with_progress()`][background_with_progress] is
+/// given one of these. Each message sent with it is passed to
+/// [`Component::update()`][update] on the main loop, in order, ahead of the
+/// message the job returns. At most 16 messages can be waiting to be
+/// delivered, so a job which sends them faster than the main loop can keep up
+/// is slowed down to match, rather than queueing them up without limit.
+///
+/// Once the component has unmounted, nothing is listening any more, which
+/// a long running job can find out with [`Progress::is_cancelled()`][is_cancelled]
+/// and give up.
+///
+/// [background_with_progress]: enum.UpdateAction.html#method.background_with_progress
+/// [update]: trait.Component.html#method.update
+/// [is_cancelled]: #method.is_cancelled
+pub struct Progress<Message> {
+    sender: Mutex<Sender<Message>>,
+}
+
+impl<Message> Progress<Message> {
+    /// Send a progress message to the component.
+    ///
+    /// If 16 messages are already waiting to be delivered, this blocks until
+    /// the main loop has taken one of them.
+    ///
+    /// Returns `false` if the job has been cancelled, and the message was
+    /// dropped.
+    pub fn send(&self, message: Message) -> bool {
+        let mut sender = self.sender.lock().unwrap();
+        block_on(sender.send(message)).is_ok()
+    }
+
+    /// Test whether the job has been cancelled, because the component which
+    /// started it has unmounted.
+    pub fn is_cancelled(&self) -> bool {
+        self.sender.lock().unwrap().is_closed()
+    }
+}
+
+impl<Message> Debug for Progress<Message> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "Progress()")
+    }
+}
+
+/// Run `job` on the background thread pool, returning a stream of its
+/// progress messages followed by its result.
+///
+/// Dropping the stream cancels the job.
+pub(crate) fn spawn<Message, F>(job: F) -> Receiver<Message>
+where
+    Message: Send + 'static,
+    F: FnOnce(&Progress<Message>) -> Message + Send + 'static,
+{
+    // The channel holds one message for each sender on top of its buffer.
+    let (sender, receiver) = channel(PROGRESS_BOUND - 1);
+    let progress = Progress {
+        sender: Mutex::new(sender),
+    };
+    POOL.with(|pool| {
+        pool.push(move || {
+            if progress.is_cancelled() {
+                return;
+            }
+            let result = job(&progress);
+            progress.send(result);
+        })
+        .expect("unable to start a background job")
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn progress_waits_for_the_receiver() {
+        let receiver = spawn(|progress| {
+            for count in 0..100 {
+                progress.send(count);
+            }
+            100
+        });
+        let messages: Vec<u32> = block_on(receiver.collect());
+        assert_eq!((0..=100).collect::<Vec<_>>(), messages);
+    }
+}
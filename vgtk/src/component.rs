@@ -1,24 +1,31 @@
 use futures::{
     channel::mpsc::{unbounded, UnboundedSender},
-    future::FutureExt,
+    future::{abortable, AbortHandle, FutureExt},
     stream::{select, Stream},
-    task::{Context, Poll},
+    task::{Context, Poll, Waker},
     StreamExt,
 };
 use glib::{Cast, MainContext, Object, ObjectExt, WeakRef};
 use gtk::{Application, GtkApplicationExt, Widget, WidgetExt, Window};
 
-use std::any::TypeId;
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt::{Debug, Error, Formatter};
 use std::future::Future;
+use std::hash::Hash;
 use std::pin::Pin;
+use std::rc::{Rc, Weak};
 use std::sync::RwLock;
 
 use colored::Colorize;
 use log::{debug, trace};
 
+use crate::background::{self, Progress};
+use crate::scheduler::{self, Flush};
 use crate::scope::{AnyScope, Scope};
-use crate::vdom::State;
+use crate::subscription::{hash_id, Subscription};
+use crate::vdom::{Roots, State};
 use crate::vnode::VNode;
 
 /// An action resulting from a [`Component::update()`](trait.Component.html#method.update).
@@ -67,10 +74,179 @@ pub enum UpdateAction<C: Component> {
     /// # }
     /// ```
     ///
+    /// The task belongs to the component, and is cancelled if the component
+    /// unmounts before it completes.
+    ///
     /// [update]: trait.Component.html#method.update
     /// [defer]: #method.defer
     /// [Future]: https://doc.rust-lang.org/std/future/trait.Future.html
     Defer(Pin<Box<dyn Future<Output = C::Message> + 'static>>),
+    /// Run an async task like [`UpdateAction::Defer`][Defer], but first cancel
+    /// the task started with the same key, if it's still running.
+    ///
+    /// Use this when only the latest of a series of tasks matters, like the
+    /// search for the text in a search box, which is out of date as soon as the
+    /// user types another letter.
+    ///
+    /// You should call [`UpdateAction::defer_keyed()`][defer_keyed] to construct
+    /// this, rather than hashing the key yourself.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # #[derive(Clone, Debug)]
+    /// enum Message {
+    ///     Search(String),
+    ///     Found(Vec<String>),
+    /// }
+    ///
+    /// # use vgtk::{gtk, Component, VNode, UpdateAction};
+    /// # use vgtk::lib::gtk::Box;
+    /// # async fn search(_: String) -> Vec<String> { vec![] }
+    /// # #[derive(Default)]
+    /// # struct Foo { results: Vec<String> }
+    /// # impl Component for Foo {
+    /// #     type Message = Message; type Properties = ();
+    /// #     fn view(&self) -> VNode<Self> { gtk!{ <Box/> } }
+    /// fn update(&mut self, message: Self::Message) -> UpdateAction<Self> {
+    ///     match message {
+    ///         Message::Search(text) => UpdateAction::defer_keyed("search", async move {
+    ///             Message::Found(search(text).await)
+    ///         }),
+    ///         Message::Found(results) => {
+    ///             self.results = results;
+    ///             UpdateAction::Render
+    ///         }
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [Defer]: #variant.Defer
+    /// [defer_keyed]: #method.defer_keyed
+    DeferKeyed(u64, Pin<Box<dyn Future<Output = C::Message> + 'static>>),
+    /// Pass every message from a [`Stream`][Stream] to
+    /// [`Component::update()`][update], until the stream is exhausted.
+    ///
+    /// Like a deferred task, the stream is dropped if the component unmounts
+    /// first. You should call [`UpdateAction::stream()`][stream] to construct
+    /// this.
+    ///
+    /// [update]: trait.Component.html#method.update
+    /// [stream]: #method.stream
+    /// [Stream]: https://docs.rs/futures/0.3/futures/stream/trait.Stream.html
+    Stream(Pin<Box<dyn Stream<Item = C::Message> + 'static>>),
+    /// Run a function on a background thread and update again when it returns,
+    /// passing its result to [`Component::update()`][update].
+    ///
+    /// Use this for CPU bound work which would otherwise freeze the UI while
+    /// it runs. The function can send progress messages to the component
+    /// through the [`Progress`][Progress] handle it's given, and should check
+    /// [`Progress::is_cancelled()`][is_cancelled] now and then if it takes
+    /// long, as its result is no longer wanted once the component unmounts.
+    ///
+    /// The pool has as many threads as there are processors; jobs started
+    /// while they're all busy wait their turn.
+    ///
+    /// You should call [`UpdateAction::background()`][background] or
+    /// [`UpdateAction::background_with_progress()`][background_with_progress]
+    /// to construct this.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # #[derive(Clone, Debug)]
+    /// enum Message {
+    ///     Open(std::path::PathBuf),
+    ///     Progress(usize),
+    ///     Loaded(usize),
+    /// }
+    ///
+    /// # use vgtk::{gtk, Component, VNode, UpdateAction};
+    /// # use vgtk::lib::gtk::Box;
+    /// # #[derive(Default)]
+    /// # struct Foo { lines: usize }
+    /// # impl Component for Foo {
+    /// #     type Message = Message; type Properties = ();
+    /// #     fn view(&self) -> VNode<Self> { gtk!{ <Box/> } }
+    /// fn update(&mut self, message: Self::Message) -> UpdateAction<Self> {
+    ///     match message {
+    ///         Message::Open(path) => UpdateAction::background_with_progress(move |progress| {
+    ///             let text = std::fs::read_to_string(path).unwrap_or_default();
+    ///             let mut lines = 0;
+    ///             for _ in text.lines() {
+    ///                 lines += 1;
+    ///                 if lines % 100_000 == 0 && !progress.send(Message::Progress(lines)) {
+    ///                     break;
+    ///                 }
+    ///             }
+    ///             Message::Loaded(lines)
+    ///         }),
+    ///         Message::Progress(lines) | Message::Loaded(lines) => {
+    ///             self.lines = lines;
+    ///             UpdateAction::Render
+    ///         }
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [update]: trait.Component.html#method.update
+    /// [background]: #method.background
+    /// [background_with_progress]: #method.background_with_progress
+    /// [Progress]: struct.Progress.html
+    /// [is_cancelled]: struct.Progress.html#method.is_cancelled
+    Background(Box<dyn FnOnce(&Progress<C::Message>) -> C::Message + Send + 'static>),
+    /// Send a message to the parent component.
+    ///
+    /// The message must be of the parent component's message type. This is
+    /// only checked at run time, when the action is performed, and a message
+    /// of any other type, or a component without a parent, panics. You should
+    /// call [`UpdateAction::parent()`][parent] to construct this, which at
+    /// least names the parent component's type.
+    ///
+    /// [parent]: #method.parent
+    Parent(Box<dyn Any>),
+    /// Perform several actions, in order.
+    ///
+    /// The component renders at most once, however many of the actions ask it
+    /// to. You can also combine actions with
+    /// [`UpdateAction::and()`][and].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # #[derive(Clone, Debug)]
+    /// enum Message {
+    ///     Edit(String),
+    ///     Saved,
+    /// }
+    ///
+    /// # use vgtk::{gtk, Component, VNode, UpdateAction};
+    /// # use vgtk::lib::gtk::Box;
+    /// # async fn save(_: String) {}
+    /// # #[derive(Default)]
+    /// # struct Foo { text: String }
+    /// # impl Component for Foo {
+    /// #     type Message = Message; type Properties = ();
+    /// #     fn view(&self) -> VNode<Self> { gtk!{ <Box/> } }
+    /// fn update(&mut self, message: Self::Message) -> UpdateAction<Self> {
+    ///     match message {
+    ///         Message::Edit(text) => {
+    ///             self.text = text.clone();
+    ///             UpdateAction::render_and_defer(async move {
+    ///                 save(text).await;
+    ///                 Message::Saved
+    ///             })
+    ///         }
+    ///         Message::Saved => UpdateAction::None,
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// [and]: #method.and
+    Batch(Vec<UpdateAction<C>>),
 }
 
 impl<C: Component> UpdateAction<C> {
@@ -80,6 +256,80 @@ impl<C: Component> UpdateAction<C> {
     pub fn defer(job: impl Future<Output = C::Message> + 'static) -> Self {
         UpdateAction::Defer(job.boxed_local())
     }
+
+    /// Construct a deferred action given a [`Future`][Future], which cancels
+    /// the action previously deferred with the same `key` if it hasn't completed.
+    ///
+    /// The `key` can be any value which implements [`Hash`][Hash].
+    ///
+    /// [Future]: https://doc.rust-lang.org/std/future/trait.Future.html
+    /// [Hash]: https://doc.rust-lang.org/std/hash/trait.Hash.html
+    pub fn defer_keyed<K: Hash + 'static>(
+        key: K,
+        job: impl Future<Output = C::Message> + 'static,
+    ) -> Self {
+        UpdateAction::DeferKeyed(hash_id(key), job.boxed_local())
+    }
+
+    /// Construct an action which re-renders the widget tree and runs an async
+    /// task given a [`Future`][Future].
+    ///
+    /// [Future]: https://doc.rust-lang.org/std/future/trait.Future.html
+    pub fn render_and_defer(job: impl Future<Output = C::Message> + 'static) -> Self {
+        UpdateAction::Batch(vec![UpdateAction::Render, Self::defer(job)])
+    }
+
+    /// Construct an action which passes every message from a [`Stream`][Stream]
+    /// to [`Component::update()`][update].
+    ///
+    /// [update]: trait.Component.html#method.update
+    /// [Stream]: https://docs.rs/futures/0.3/futures/stream/trait.Stream.html
+    pub fn stream(stream: impl Stream<Item = C::Message> + 'static) -> Self {
+        UpdateAction::Stream(stream.boxed_local())
+    }
+
+    /// Construct an action which runs `job` on a background thread.
+    pub fn background(job: impl FnOnce() -> C::Message + Send + 'static) -> Self {
+        UpdateAction::Background(Box::new(move |_: &Progress<C::Message>| job()))
+    }
+
+    /// Construct an action which runs `job` on a background thread, giving it
+    /// a [`Progress`][Progress] handle to send progress messages through.
+    ///
+    /// [Progress]: struct.Progress.html
+    pub fn background_with_progress(
+        job: impl FnOnce(&Progress<C::Message>) -> C::Message + Send + 'static,
+    ) -> Self {
+        UpdateAction::Background(Box::new(job))
+    }
+
+    /// Construct an action which sends a message to the parent component,
+    /// which must be a `P`.
+    ///
+    /// # Panics
+    ///
+    /// When the action is performed, if the component has no parent, or its
+    /// parent isn't a `P`.
+    pub fn parent<P>(message: P::Message) -> Self
+    where
+        P: 'static + Component,
+        P::Message: 'static,
+    {
+        UpdateAction::Parent(Box::new(message))
+    }
+
+    /// Combine this action with another, to be performed after it.
+    pub fn and(self, other: Self) -> Self {
+        match (self, other) {
+            (UpdateAction::None, other) => other,
+            (action, UpdateAction::None) => action,
+            (UpdateAction::Batch(mut actions), other) => {
+                actions.push(other);
+                UpdateAction::Batch(actions)
+            }
+            (action, other) => UpdateAction::Batch(vec![action, other]),
+        }
+    }
 }
 
 impl<C, F> From<F> for UpdateAction<C>
@@ -141,7 +391,7 @@ pub trait Component: Default + Unpin {
     ///
     /// If you need to perform I/O, you can return `UpdateAction::Defer`, which
     /// will run an async action and call `Component::update()` again with its
-    /// result.
+    /// result. To do both, or more, return `UpdateAction::Batch`.
     ///
     /// Otherwise, return `UpdateAction::None`.
     fn update(&mut self, _msg: Self::Message) -> UpdateAction<Self> {
@@ -169,6 +419,56 @@ pub trait Component: Default + Unpin {
         unimplemented!("add a Component::change() implementation")
     }
 
+    /// Decide whether a subcomponent should act on new properties from its
+    /// parent.
+    ///
+    /// This is called with the properties the component last received and the
+    /// new ones whenever its parent renders. If it returns `false`, the new
+    /// properties are discarded: `Component::change()` isn't called and the
+    /// component doesn't render. The default implementation always returns `true`.
+    ///
+    /// Note that a [`Callback`][Callback] is rebuilt every time the parent renders,
+    /// and never compares equal to the previous one, so you'll usually want to
+    /// leave callbacks out of the comparison.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use vgtk::{gtk, Component, VNode, UpdateAction};
+    /// # use vgtk::lib::gtk::{Label, LabelExt};
+    /// #[derive(Clone, Debug, Default, PartialEq)]
+    /// pub struct Counter {
+    ///     pub count: usize,
+    /// }
+    ///
+    /// impl Component for Counter {
+    ///     type Message = ();
+    ///     type Properties = Self;
+    ///
+    ///     fn create(props: Self) -> Self {
+    ///         props
+    ///     }
+    ///
+    ///     fn change(&mut self, props: Self) -> UpdateAction<Self> {
+    ///         *self = props;
+    ///         UpdateAction::Render
+    ///     }
+    ///
+    ///     fn should_render(&self, old_props: &Self, new_props: &Self) -> bool {
+    ///         old_props != new_props
+    ///     }
+    ///
+    ///     fn view(&self) -> VNode<Self> {
+    ///         gtk! { <Label label=self.count.to_string() /> }
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [Callback]: struct.Callback.html
+    fn should_render(&self, _old_props: &Self::Properties, _new_props: &Self::Properties) -> bool {
+        true
+    }
+
     /// This method is called when the `Component` becomes visible to the user.
     ///
     /// The default implementation does nothing. You can reimplement it if you
@@ -182,6 +482,24 @@ pub trait Component: Default + Unpin {
     /// need to be aware of when this happens.
     fn unmounted(&mut self) {}
 
+    /// List the streams of messages the `Component` wants to receive.
+    ///
+    /// This is called when the `Component` starts and again after every call to
+    /// [`update()`][update] or [`change()`][change]. Subscriptions which weren't
+    /// in the previous list are started, and those which have gone from it are
+    /// stopped, as told apart by their ids. Every subscription is stopped when
+    /// the `Component` unmounts. See [`Subscription`][Subscription] for an
+    /// example.
+    ///
+    /// The default implementation subscribes to nothing.
+    ///
+    /// [update]: #method.update
+    /// [change]: #method.change
+    /// [Subscription]: struct.Subscription.html
+    fn subscriptions(&self) -> Vec<Subscription<Self::Message>> {
+        Vec::new()
+    }
+
     /// Build a `VNode` tree to represent your UI.
     ///
     /// This is called whenever the `Component` needs to re-render, and its UI
@@ -281,16 +599,33 @@ where
             Some(ref p) => p.inherit(type_name, user_send),
             None => Scope::new(type_name, user_send),
         };
-        let state = C::create(props);
+        // `create()` runs in the new component's own context, so it can find
+        // its parent.
+        let state = with_context(
+            LocalContext {
+                parent_scope: parent_scope.map(|scope| scope.clone().into()),
+                parent_object: parent.map(ObjectExt::downgrade),
+                current_object: None,
+            },
+            || C::create(props.clone()),
+        );
         let initial_view = state.view();
         let ui_state = State::build_root(&initial_view, parent, &scope);
         PartialComponentTask {
             task: ComponentTask {
                 scope,
                 parent_scope: parent_scope.cloned(),
+                parent: parent.map(ObjectExt::downgrade),
+                roots: Default::default(),
+                props,
                 state,
                 ui_state: Some(ui_state),
                 channel,
+                dirty: false,
+                finished: false,
+                waker: None,
+                subscriptions: HashMap::new(),
+                jobs: Default::default(),
             },
             view: initial_view,
             sender: sys_send,
@@ -304,12 +639,17 @@ where
     ) -> (UnboundedSender<ComponentMessage<C>>, ComponentTask<C, P>) {
         if let Some(ref mut ui_state) = self.task.ui_state {
             ui_state.build_children(&self.view, &self.task.scope);
+            self.task.roots.borrow_mut().update(ui_state);
         }
         (self.sender, self.task)
     }
 
     pub(crate) fn object(&self) -> Object {
-        self.task.ui_state.as_ref().unwrap().object().clone()
+        self.task
+            .ui_state
+            .as_ref()
+            .and_then(State::object)
+            .expect("component has no root object")
     }
 
     pub(crate) fn scope(&self) -> Scope<C> {
@@ -324,9 +664,19 @@ where
 {
     scope: Scope<C>,
     parent_scope: Option<Scope<P>>,
+    parent: Option<WeakRef<Object>>,
+    roots: Rc<RefCell<Roots>>,
+    props: C::Properties,
     state: C,
     ui_state: Option<State<C>>,
     channel: Pin<Box<dyn Stream<Item = ComponentMessage<C>>>>,
+    // Whether the component has asked to render since it last rendered.
+    dirty: bool,
+    finished: bool,
+    waker: Option<Waker>,
+    // The running subscriptions, by id.
+    subscriptions: HashMap<u64, AbortHandle>,
+    jobs: Jobs,
 }
 
 impl<C, P> ComponentTask<C, P>
@@ -342,15 +692,117 @@ where
         PartialComponentTask::new(props, parent, parent_scope).finalise()
     }
 
-    fn run_job(&self, job: impl Future<Output = C::Message> + 'static) {
+    fn defer(&mut self, key: Option<u64>, job: impl Future<Output = C::Message> + 'static) {
         let scope = self.scope.clone();
-        MainContext::ref_thread_default().spawn_local(async move {
-            scope.send_message(job.await);
-        })
+        self.jobs.spawn(self.scope.name(), key, async move {
+            deliver(&scope, job.await);
+        });
     }
 
+    fn forward(&mut self, mut stream: impl Stream<Item = C::Message> + Unpin + 'static) {
+        let scope = self.scope.clone();
+        self.jobs.spawn(self.scope.name(), None, async move {
+            while let Some(message) = stream.next().await {
+                if !deliver(&scope, message) {
+                    break;
+                }
+            }
+        });
+    }
+
+    fn send_to_parent(&self, message: Box<dyn Any>) {
+        let scope = match &self.parent_scope {
+            Some(scope) => scope,
+            None => panic!(
+                "{} has no parent component to send a message to!",
+                self.scope.name()
+            ),
+        };
+        match message.downcast::<P::Message>() {
+            Ok(message) => {
+                deliver(scope, *message);
+            }
+            Err(_) => panic!(
+                "unexpected message type for parent component {} of {}",
+                scope.name(),
+                self.scope.name()
+            ),
+        }
+    }
+
+    fn act(&mut self, action: UpdateAction<C>) {
+        match action {
+            UpdateAction::Defer(job) => {
+                self.defer(None, job);
+            }
+            UpdateAction::DeferKeyed(key, job) => {
+                self.defer(Some(key), job);
+            }
+            UpdateAction::Stream(stream) => {
+                self.forward(stream);
+            }
+            UpdateAction::Parent(message) => {
+                self.send_to_parent(message);
+            }
+            UpdateAction::Background(job) => {
+                self.forward(background::spawn(job));
+            }
+            UpdateAction::Batch(actions) => {
+                for action in actions {
+                    self.act(action);
+                }
+            }
+            UpdateAction::Render => {
+                self.dirty = true;
+            }
+            UpdateAction::None => {}
+        }
+    }
+
+    /// Start the subscriptions the component asks for which aren't running, and
+    /// stop the running ones it no longer asks for.
+    fn subscribe(&mut self) {
+        let mut running = std::mem::take(&mut self.subscriptions);
+        for subscription in self.state.subscriptions() {
+            let id = subscription.id();
+            if self.subscriptions.contains_key(&id) {
+                continue;
+            }
+            let handle = match running.remove(&id) {
+                Some(handle) => handle,
+                None => {
+                    trace!(
+                        "{} {}",
+                        self.scope.name().magenta().bold(),
+                        format!("starting {:?}", subscription).bright_black()
+                    );
+                    subscription.start(&self.scope)
+                }
+            };
+            self.subscriptions.insert(id, handle);
+        }
+        for (id, handle) in running {
+            trace!(
+                "{} {}",
+                self.scope.name().magenta().bold(),
+                format!("stopping Subscription({:016x})", id).bright_black()
+            );
+            handle.abort();
+        }
+    }
+
+    /// Stop every subscription and deferred job, so nothing sends the component
+    /// any more messages.
+    fn cancel_all(&mut self) {
+        for (_, handle) in self.subscriptions.drain() {
+            handle.abort();
+        }
+        self.jobs.abort_all();
+    }
+
+    /// Process every message waiting in the channel. Rendering is left to the
+    /// scheduler.
     pub(crate) fn process(&mut self, ctx: &mut Context<'_>) -> Poll<()> {
-        let mut render = false;
         loop {
             let next = Stream::poll_next(self.channel.as_mut(), ctx);
             trace!(
@@ -360,24 +812,20 @@ where
             );
             match next {
                 Poll::Ready(Some(msg)) => match msg {
-                    ComponentMessage::Update(msg) => match self.state.update(msg) {
-                        UpdateAction::Defer(job) => {
-                            self.run_job(job);
-                        }
-                        UpdateAction::Render => {
-                            render = true;
-                        }
-                        UpdateAction::None => {}
-                    },
-                    ComponentMessage::Props(props) => match self.state.change(props) {
-                        UpdateAction::Defer(job) => {
-                            self.run_job(job);
-                        }
-                        UpdateAction::Render => {
-                            render = true;
+                    ComponentMessage::Update(msg) => {
+                        let action = self.state.update(msg);
+                        self.act(action);
+                        self.subscribe();
+                    }
+                    ComponentMessage::Props(props) => {
+                        if !self.state.should_render(&self.props, &props) {
+                            continue;
                         }
-                        UpdateAction::None => {}
-                    },
+                        self.props = props.clone();
+                        let action = self.state.change(props);
+                        self.act(action);
+                        self.subscribe();
+                    }
                     ComponentMessage::Mounted => {
                         debug!(
                             "{} {}",
@@ -385,48 +833,31 @@ where
                             self.scope.name().magenta().bold()
                         );
                         self.state.mounted();
+                        self.subscribe();
                     }
                     ComponentMessage::Unmounted => {
                         if let Some(state) = self.ui_state.take() {
                             state.unmount();
                         }
+                        self.cancel_all();
                         self.state.unmounted();
                         debug!(
                             "{} {}",
                             "Component unmounted:".bright_red(),
                             self.scope.name().magenta().bold()
                         );
+                        self.finished = true;
                         return Poll::Ready(());
                     }
                 },
-                Poll::Pending if render => {
-                    if let Some(ref mut ui_state) = self.ui_state {
-                        // we patch
-                        let new_view = self.state.view();
-                        self.scope.mute();
-                        if !ui_state.patch(&new_view, None, &self.scope) {
-                            unimplemented!(
-                                "{}: don't know how to propagate failed patch",
-                                self.scope.name()
-                            );
-                        }
-                        self.scope.unmute();
-                        return Poll::Pending;
-                    } else {
-                        debug!(
-                            "{} {}",
-                            self.scope.name().magenta().bold(),
-                            "rendering in the absence of a UI state; exiting".bright_red()
-                        );
-                        return Poll::Ready(());
-                    }
-                }
                 Poll::Ready(None) => {
                     debug!(
                         "{} {}",
                         self.scope.name().magenta().bold(),
                         "terminating because all channel handles dropped".bright_red()
                     );
+                    self.cancel_all();
+                    self.finished = true;
                     return Poll::Ready(());
                 }
                 Poll::Pending => return Poll::Pending,
@@ -434,8 +865,86 @@ where
         }
     }
 
+    /// Render the component, if it's asked to since it last rendered.
+    fn render(&mut self) {
+        if !std::mem::replace(&mut self.dirty, false) {
+            return;
+        }
+        if let Some(mut ui_state) = self.ui_state.take() {
+            // we patch
+            let new_view = self.state.view();
+            let parent = self.parent.as_ref().and_then(WeakRef::upgrade);
+            let anchor = self.roots.borrow().anchor();
+            self.scope.mute();
+            if !ui_state.patch_root(&new_view, parent.as_ref(), &anchor, &self.scope) {
+                // The root node can't be patched, usually because
+                // its type changed, so we build a new one in its
+                // place. The parent sees the new root objects
+                // through our roots.
+                debug!(
+                    "{} {}",
+                    self.scope.name().magenta().bold(),
+                    "root node changed; rebuilding".bright_yellow()
+                );
+                let child_props = self.roots.borrow().child_props();
+                ui_state = ui_state.replace_root(
+                    &new_view,
+                    parent.as_ref(),
+                    &anchor,
+                    &child_props,
+                    &self.scope,
+                );
+            }
+            self.roots.borrow_mut().update(&ui_state);
+            self.ui_state = Some(ui_state);
+            self.scope.unmute();
+        } else {
+            debug!(
+                "{} {}",
+                self.scope.name().magenta().bold(),
+                "rendering in the absence of a UI state; exiting".bright_red()
+            );
+            self.finished = true;
+        }
+    }
+
+    /// Run `f` with the thread's local context set up for this component.
+    fn in_context<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        LOCAL_CONTEXT.with(|key| {
+            *key.write().unwrap() = LocalContext {
+                parent_scope: self.parent_scope.as_ref().map(|scope| scope.clone().into()),
+                parent_object: self.parent.clone(),
+                current_object: self
+                    .ui_state
+                    .as_ref()
+                    .and_then(State::object)
+                    .map(|object| object.downgrade()),
+            };
+        });
+        let result = f(self);
+        LOCAL_CONTEXT.with(|key| {
+            *key.write().unwrap() = Default::default();
+        });
+        result
+    }
+
+    /// Start running the component on the main loop.
+    ///
+    /// Returns a handle for scheduling it to be flushed.
+    pub(crate) fn spawn(mut self) -> Weak<RefCell<dyn Flush>> {
+        self.subscribe();
+        let task = Rc::new(RefCell::new(self));
+        let handle = Rc::downgrade(&task) as Weak<RefCell<dyn Flush>>;
+        MainContext::ref_thread_default().spawn_local(RunningTask(task));
+        handle
+    }
+
     pub(crate) fn object(&self) -> Option<Object> {
-        self.ui_state.as_ref().map(|state| state.object().clone())
+        self.ui_state.as_ref().and_then(State::object)
+    }
+
+    pub(crate) fn roots(&self) -> Rc<RefCell<Roots>> {
+        self.roots.clone()
     }
 
     pub(crate) fn current_parent_scope() -> Scope<C> {
@@ -455,10 +964,87 @@ where
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum JobId {
+    Anonymous(usize),
+    Keyed(u64),
+}
+
+/// The deferred jobs a component has running, so they can be aborted when it
+/// unmounts or when a newer job with the same key replaces them.
+#[derive(Default)]
+struct Jobs {
+    next: usize,
+    // Each running job with the serial number it was started with, so a job
+    // which finishes after being replaced doesn't remove its replacement.
+    running: Rc<RefCell<HashMap<JobId, (usize, AbortHandle)>>>,
+}
+
+impl Jobs {
+    fn spawn(
+        &mut self,
+        name: &'static str,
+        key: Option<u64>,
+        job: impl Future<Output = ()> + 'static,
+    ) {
+        let serial = self.next;
+        self.next += 1;
+        let id = match key {
+            Some(key) => JobId::Keyed(key),
+            None => JobId::Anonymous(serial),
+        };
+        let (job, handle) = abortable(job);
+        if let Some((_, previous)) = self.running.borrow_mut().insert(id, (serial, handle)) {
+            trace!(
+                "{} {}",
+                name.magenta().bold(),
+                "cancelling a deferred job replaced by a newer one".bright_black()
+            );
+            previous.abort();
+        }
+        let running = Rc::downgrade(&self.running);
+        MainContext::ref_thread_default().spawn_local(async move {
+            // An aborted job has been removed already.
+            if job.await.is_err() {
+                return;
+            }
+            if let Some(running) = running.upgrade() {
+                let mut running = running.borrow_mut();
+                if let Some((current, _)) = running.get(&id) {
+                    if *current == serial {
+                        running.remove(&id);
+                    }
+                }
+            }
+        })
+    }
+
+    fn abort_all(&mut self) {
+        for (_, (_, handle)) in self.running.borrow_mut().drain() {
+            handle.abort();
+        }
+    }
+}
+
+/// Send a message from a deferred job to a component, unless the component
+/// has gone, in which case return `false`.
+fn deliver<C: 'static + Component>(scope: &Scope<C>, message: C::Message) -> bool {
+    if scope.try_send(message).is_err() {
+        debug!(
+            "{} {}",
+            scope.name().magenta().bold(),
+            "dropping a message for a component which has gone".bright_red()
+        );
+        return false;
+    }
+    true
+}
+
 /// Get the current [`Object`][Object].
 ///
 /// When called from inside a [`Component`][Component], it will return the top level [`Object`][Object]
-/// for this component, if it currently exists.
+/// for this component, if it currently exists. If the component renders a fragment, this is the
+/// first [`Object`][Object] in it.
 ///
 /// When called from outside a [`Component`][Component]'s lifecycle, you should hopefully
 /// just receive a `None`, but, generally, try not to do that.
@@ -509,9 +1095,32 @@ pub fn current_window() -> Option<Window> {
     })
 }
 
+/// Get the object the current component was placed in by its parent, if it
+/// still exists.
+pub(crate) fn current_parent_object() -> Option<Object> {
+    LOCAL_CONTEXT.with(|key| {
+        let lock = key.read().unwrap();
+        lock.parent_object
+            .as_ref()
+            .and_then(|object| object.upgrade())
+    })
+}
+
+/// Run `f` with the thread's local context set to `context`, and put back the
+/// one it replaced afterwards.
+fn with_context<R>(context: LocalContext, f: impl FnOnce() -> R) -> R {
+    let previous = LOCAL_CONTEXT.with(|key| std::mem::replace(&mut *key.write().unwrap(), context));
+    let result = f();
+    LOCAL_CONTEXT.with(|key| {
+        *key.write().unwrap() = previous;
+    });
+    result
+}
+
 #[derive(Default)]
 struct LocalContext {
     parent_scope: Option<AnyScope>,
+    parent_object: Option<WeakRef<Object>>,
     current_object: Option<WeakRef<Object>>,
 }
 
@@ -519,7 +1128,34 @@ thread_local! {
     static LOCAL_CONTEXT: RwLock<LocalContext> = RwLock::new(Default::default())
 }
 
-impl<C, P> Future for ComponentTask<C, P>
+impl<C, P> Flush for ComponentTask<C, P>
+where
+    C: 'static + Component,
+    P: 'static + Component,
+{
+    fn flush(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.in_context(|task| {
+            // Catch up on any messages first, using the waker from the last
+            // poll so the main loop still wakes the task for new ones.
+            if let Some(waker) = task.waker.clone() {
+                if task.process(&mut Context::from_waker(&waker)).is_ready() {
+                    waker.wake();
+                    return;
+                }
+            }
+            task.render();
+        });
+    }
+}
+
+/// A `ComponentTask` running on the main loop, which it shares with the
+/// scheduler.
+struct RunningTask<C: Component, P: Component>(Rc<RefCell<ComponentTask<C, P>>>);
+
+impl<C, P> Future for RunningTask<C, P>
 where
     C: 'static + Component,
     P: 'static + Component,
@@ -527,19 +1163,18 @@ where
     type Output = ();
 
     fn poll(self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Self::Output> {
-        LOCAL_CONTEXT.with(|key| {
-            *key.write().unwrap() = LocalContext {
-                parent_scope: self.parent_scope.as_ref().map(|scope| scope.clone().into()),
-                current_object: self
-                    .ui_state
-                    .as_ref()
-                    .map(|state| state.object().downgrade()),
-            };
-        });
-        let polled = self.get_mut().process(ctx);
-        LOCAL_CONTEXT.with(|key| {
-            *key.write().unwrap() = Default::default();
-        });
+        let mut task = self.0.borrow_mut();
+        task.waker = Some(ctx.waker().clone());
+        let polled = task.in_context(|task| task.process(ctx));
+        if task.finished {
+            return Poll::Ready(());
+        }
+        if task.dirty {
+            let depth = task.scope.depth();
+            drop(task);
+            let handle = Rc::downgrade(&self.0) as Weak<RefCell<dyn Flush>>;
+            scheduler::schedule(handle, depth);
+        }
         polled
     }
 }
@@ -9,22 +9,62 @@
 
 use cairo::Surface;
 use gdk_pixbuf::Pixbuf;
-use gio::{Action, ActionExt, ApplicationFlags};
-use glib::{GString, IsA, Object, ObjectExt};
+use gio::{
+    Action, ActionExt, ActionMapExt, ApplicationFlags, MenuModel, SimpleAction, SimpleActionGroup,
+    ThemedIcon,
+};
+use glib::variant::FromVariant;
+use glib::{Cast, GString, IsA, Object, ObjectExt};
+use glib::{SignalHandlerId, StaticVariantType, ToValue, ToVariant, Variant};
+use gtk::prelude::GtkListStoreExtManual;
 use gtk::{
-    Application, ApplicationWindowExt, BoxExt, GridExt, GtkApplicationExt, GtkWindowExt,
-    HeaderBarExt, ImageExt, LabelExt, NotebookExt, Widget, Window, WindowPosition, WindowType,
+    Application, ApplicationWindowExt, BoxExt, CellLayoutExt, CellRenderer, ComboBoxExt,
+    ComboBoxTextExt, Container, ContainerExt, DialogExt, GridExt, GtkApplicationExt,
+    GtkListStoreExt, GtkWindowExt, HeaderBarExt, ImageExt, LabelExt, ListStore, NotebookExt,
+    OverlayExt, ResponseType, TreeIter, TreeModelExt, TreePath, TreeViewColumnExt, TreeViewExt,
+    Widget, WidgetExt, Window, WindowPosition, WindowType,
 };
 
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+
 use colored::Colorize;
 use log::trace;
 
-use crate::types::GridPosition;
+use crate::shortcuts::{with_window_shortcuts, Shortcuts};
+use crate::types::{GridPosition, TreeRow};
+use crate::vdom::longest_increasing_subsequence;
 
 /// Helper trait for [`Application`][Application].
 ///
+/// The `accels` and `shortcut_title` child properties of an action declare
+/// its keyboard shortcuts in the application's [`Shortcuts`][Shortcuts]
+/// registry, as do those of an [`ApplicationWindow`][ApplicationWindow]'s
+/// actions.
+///
+/// `accels` takes a slice of strings, or a `Vec` of them, which can be built
+/// at runtime, from the user's settings for instance.
+///
+/// ```rust,no_run
+/// # use vgtk::{ext::*, gtk, VNode};
+/// # use vgtk::lib::gio::{ApplicationFlags, SimpleAction};
+/// # use vgtk::lib::gtk::{Application, ApplicationWindow};
+/// # fn view(quit: &[String]) -> VNode<()> {
+/// gtk! {
+///     <Application::new_unwrap(None, ApplicationFlags::empty())>
+///         <SimpleAction::new("quit", None) Application::accels=quit.to_vec() />
+///         <ApplicationWindow>
+///             <SimpleAction::new("save", None) ApplicationWindow::accels=vec!["<Ctrl>s"] />
+///         </ApplicationWindow>
+///     </Application>
+/// }
+/// # }
+/// ```
+///
 /// [Application]: ../../gtk/struct.Application.html
-pub trait ApplicationHelpers: GtkApplicationExt {
+/// [ApplicationWindow]: ../../gtk/struct.ApplicationWindow.html
+/// [Shortcuts]: ../struct.Shortcuts.html
+pub trait ApplicationHelpers: GtkApplicationExt + IsA<Application> {
     /// Construct a new [`Application`][Application] and panic if it fails.
     ///
     /// This is like [`Application::new`][new], but returns an [`Application`][Application] instead of
@@ -40,84 +80,85 @@ pub trait ApplicationHelpers: GtkApplicationExt {
     }
 
     fn get_child_accels<P: IsA<Action>>(&self, action: &P) -> Vec<GString> {
-        self.get_accels_for_action(&format!(
-            "app.{}",
-            action
-                .as_ref()
-                .get_name()
-                .expect("Action has no name")
-                .as_str()
-        ))
+        let shortcuts = Shortcuts::for_application(self.upcast_ref());
+        let accels = shortcuts.declared(&detailed_name("app", action));
+        accels.into_iter().map(GString::from).collect()
+    }
+
+    fn set_child_accels<P: IsA<Action>, S: AsRef<str>>(&self, action: &P, accels: &[S]) {
+        let accels: Vec<&str> = accels.iter().map(AsRef::as_ref).collect();
+        Shortcuts::for_application(self.upcast_ref())
+            .declare(&detailed_name("app", action), &accels);
+    }
+
+    fn get_child_shortcut_title<P: IsA<Action>>(&self, action: &P) -> String {
+        Shortcuts::for_application(self.upcast_ref()).title(&detailed_name("app", action))
     }
 
-    fn set_child_accels<P: IsA<Action>>(&self, action: &P, accels: &[&str]) {
-        self.set_accels_for_action(
-            &format!(
-                "app.{}",
-                action
-                    .as_ref()
-                    .get_name()
-                    .expect("Action has no name")
-                    .as_str()
-            ),
-            accels,
-        )
+    fn set_child_shortcut_title<P: IsA<Action>>(&self, action: &P, title: &str) {
+        Shortcuts::for_application(self.upcast_ref())
+            .set_title(&detailed_name("app", action), title);
     }
 }
 
-impl<A> ApplicationHelpers for A where A: GtkApplicationExt {}
+impl<A> ApplicationHelpers for A where A: GtkApplicationExt + IsA<Application> {}
+
+// The name of an action, with the prefix of the action map it's in.
+fn detailed_name<P: IsA<Action>>(prefix: &str, action: &P) -> String {
+    format!(
+        "{}.{}",
+        prefix,
+        action
+            .as_ref()
+            .get_name()
+            .expect("Action has no name")
+            .as_str()
+    )
+}
 
 /// Helper trait for [`ApplicationWindow`][ApplicationWindow].
 ///
 /// [ApplicationWindow]: ../../gtk/struct.ApplicationWindow.html
 pub trait ApplicationWindowHelpers: ApplicationWindowExt + GtkWindowExt + IsA<Window> {
     fn get_child_accels<P: IsA<Action>>(&self, action: &P) -> Vec<GString> {
-        let app = self
-            .get_application()
-            .expect("ApplicationWindow has no Application!");
-        app.get_accels_for_action(&format!(
-            "win.{}",
-            action
-                .as_ref()
-                .get_name()
-                .expect("Action has no name")
-                .as_str()
-        ))
-    }
-
-    fn set_child_accels<P: IsA<Action>>(&self, action: &P, accels: &'static [&str]) {
-        let name = format!(
-            "win.{}",
-            action
-                .as_ref()
-                .get_name()
-                .expect("Action has no name")
-                .as_str()
-        );
-        if let Some(app) = self.get_application() {
-            app.set_accels_for_action(&name, accels);
-        } else {
-            use std::cell::Cell;
-            use std::rc::Rc;
-
-            let id = Rc::new(Cell::new(None));
-            let inner_id = id.clone();
-            id.set(Some(self.connect_property_application_notify(
-                move |window: &Self| {
-                    if let Some(app) = window.get_application() {
-                        trace!(
-                            "{} {} -> {}",
-                            "Action:".bright_black(),
-                            name.bright_cyan().bold(),
-                            format!("{:?}", accels).bright_green().bold()
-                        );
-                        app.set_accels_for_action(&name, accels);
-                        window.disconnect(inner_id.replace(None).unwrap());
-                    }
-                },
-            )));
+        let accels = match self.get_application() {
+            Some(app) => Shortcuts::for_application(&app).declared(&detailed_name("win", action)),
+            None => Vec::new(),
+        };
+        accels.into_iter().map(GString::from).collect()
+    }
+
+    fn set_child_accels<P: IsA<Action>, S: AsRef<str>>(&self, action: &P, accels: &[S]) {
+        let name = detailed_name("win", action);
+        let accels: Vec<String> = accels
+            .iter()
+            .map(|accel| accel.as_ref().to_string())
+            .collect();
+        with_window_shortcuts(self, move |shortcuts| {
+            trace!(
+                "{} {} -> {}",
+                "Action:".bright_black(),
+                name.bright_cyan().bold(),
+                format!("{:?}", accels).bright_green().bold()
+            );
+            let accels: Vec<&str> = accels.iter().map(String::as_str).collect();
+            shortcuts.declare(&name, &accels);
+        });
+    }
+
+    fn get_child_shortcut_title<P: IsA<Action>>(&self, action: &P) -> String {
+        let name = detailed_name("win", action);
+        match self.get_application() {
+            Some(app) => Shortcuts::for_application(&app).title(&name),
+            None => String::new(),
         }
     }
+
+    fn set_child_shortcut_title<P: IsA<Action>>(&self, action: &P, title: &str) {
+        let name = detailed_name("win", action);
+        let title = title.to_string();
+        with_window_shortcuts(self, move |shortcuts| shortcuts.set_title(&name, &title));
+    }
 }
 
 impl<A> ApplicationWindowHelpers for A where A: ApplicationWindowExt + GtkWindowExt + IsA<Window> {}
@@ -165,6 +206,15 @@ pub trait WindowExtHelpers: GtkWindowExt {
     fn set_window_position(&self, window_position: WindowPosition) {
         self.set_property_window_position(window_position)
     }
+
+    fn get_child_titlebar<P: IsA<Object>>(&self, _child: &P) -> bool {
+        // Always compare true, it's all taken care of in add_child().
+        true
+    }
+
+    fn set_child_titlebar<P: IsA<Object>>(&self, _child: &P, _titlebar: bool) {
+        // This is handled by add_child() rules. The setter is a no-op.
+    }
 }
 
 impl<A> WindowExtHelpers for A where A: GtkWindowExt {}
@@ -185,6 +235,132 @@ pub trait BoxExtHelpers: BoxExt {
 
 impl<A> BoxExtHelpers for A where A: BoxExt {}
 
+/// Helper trait for [`ComboBoxText`][ComboBoxText].
+///
+/// The `items` property holds the `(id, text)` pair of each item. When it
+/// changes, the items the old and new lists have in common are left alone, so
+/// the active item stays active unless it goes away. Set `active_id` to choose
+/// the active item; it's always set after `items`.
+///
+/// ```rust,no_run
+/// # use vgtk::{ext::*, gtk, Component, VNode};
+/// # use vgtk::lib::gtk::*;
+/// # #[derive(Clone, Debug)] enum Message { Select(Option<String>) }
+/// # #[derive(Default)] struct Picker { fruits: Vec<(String, String)>, selected: String }
+/// # impl Component for Picker { type Message = Message; type Properties = ();
+/// # fn view(&self) -> VNode<Self> {
+/// gtk! {
+///     <ComboBoxText items=self.fruits.clone() active_id=self.selected.as_str()
+///                   on changed = |combo| Message::Select(combo.get_active_id().map(String::from)) />
+/// }
+/// # }}
+/// ```
+///
+/// [ComboBoxText]: ../../gtk/struct.ComboBoxText.html
+pub trait ComboBoxTextExtHelpers: ComboBoxTextExt + ComboBoxExt {
+    fn get_items(&self) -> Vec<(String, String)> {
+        let mut items = Vec::new();
+        let model = match self.get_model() {
+            Some(model) => model,
+            None => return items,
+        };
+        let column = |iter, column| {
+            model
+                .get_value(iter, column)
+                .get::<String>()
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        };
+        if let Some(iter) = model.get_iter_first() {
+            loop {
+                // ComboBoxText keeps the text in column 0 and the ID in column 1.
+                items.push((column(&iter, 1), column(&iter, 0)));
+                if !model.iter_next(&iter) {
+                    break;
+                }
+            }
+        }
+        items
+    }
+
+    fn set_items(&self, items: &[(String, String)]) {
+        let active = self.get_active_id();
+        let wanted: HashSet<&str> = items.iter().map(|(id, _)| id.as_str()).collect();
+        let mut current = self.get_items();
+        for (position, item) in items.iter().enumerate() {
+            // Drop the items which have gone away, up to the next one which
+            // hasn't.
+            while position < current.len() && !wanted.contains(current[position].0.as_str()) {
+                self.remove(position as i32);
+                current.remove(position);
+            }
+            match current.get(position) {
+                Some(existing) if existing == item => continue,
+                Some((id, _)) if *id == item.0 => {
+                    // Its text has changed, so it has to be replaced.
+                    self.remove(position as i32);
+                    current.remove(position);
+                }
+                _ => {}
+            }
+            // Anything which was here moves down, and if it's wanted further
+            // on, it's either found there or removed with the leftovers.
+            self.insert(position as i32, Some(&item.0), &item.1);
+            current.insert(position, item.clone());
+        }
+        for position in (items.len()..current.len()).rev() {
+            self.remove(position as i32);
+        }
+        // If the active item had to be replaced, make its replacement active.
+        if let Some(active) = active {
+            if wanted.contains(active.as_str()) && self.get_active_id().as_ref() != Some(&active) {
+                self.set_active_id(Some(&active));
+            }
+        }
+    }
+}
+
+impl<A> ComboBoxTextExtHelpers for A where A: ComboBoxTextExt + ComboBoxExt {}
+
+/// Helper trait for [`Dialog`][Dialog].
+///
+/// [Dialog]: ../../gtk/struct.Dialog.html
+pub trait DialogExtHelpers: DialogExt + GtkWindowExt {
+    fn get_default_response(&self) -> ResponseType {
+        self.get_default_widget()
+            .map_or(ResponseType::None, |widget| {
+                self.get_response_for_widget(&widget)
+            })
+    }
+
+    fn get_child_response<P: IsA<Widget>>(&self, child: &P) -> ResponseType {
+        self.get_response_for_widget(child)
+    }
+
+    fn set_child_response<P: IsA<Widget>>(&self, child: &P, response: ResponseType) {
+        // add_child() puts the child in the action area with its response.
+        // Adding it again is the only way to give it a different one.
+        if self.get_response_for_widget(child) == response {
+            return;
+        }
+        if let Some(area) = child.get_parent() {
+            let position = area
+                .downcast_ref::<gtk::Box>()
+                .map(|area| area.get_child_position(child));
+            if let Some(area) = area.downcast_ref::<Container>() {
+                area.remove(child);
+            }
+            self.add_action_widget(child, response);
+            if let (Some(area), Some(position)) = (area.downcast_ref::<gtk::Box>(), position) {
+                area.reorder_child(child, position);
+            }
+        }
+    }
+}
+
+impl<A> DialogExtHelpers for A where A: DialogExt + GtkWindowExt {}
+
 /// Helper trait for [`HeaderBar`][HeaderBar].
 ///
 /// [HeaderBar]: ../../gtk/struct.HeaderBar.html
@@ -227,6 +403,408 @@ pub trait LabelExtHelpers: LabelExt {
 
 impl<A> LabelExtHelpers for A where A: LabelExt {}
 
+// Where set_rows() keeps the rows it bound to a model.
+const ROWS_KEY: &str = "vgtk-rows";
+
+// Look at the rows last bound to a model with set_rows(), or an empty list if
+// it has no rows of this type.
+fn with_rows<R: TreeRow, O: ObjectExt, T>(model: &O, f: impl FnOnce(&[R]) -> T) -> T {
+    // Only set_rows() stores anything under this key, always as a boxed Any.
+    #[allow(unsafe_code)]
+    let rows = unsafe { model.get_data::<Box<dyn Any>>(ROWS_KEY) };
+    let rows = rows.and_then(|rows| rows.downcast_ref::<Vec<R>>());
+    f(rows.map_or(&[], Vec::as_slice))
+}
+
+// Forget the rows bound to a model with set_rows(), and empty it.
+pub(crate) fn clear_rows(store: &ListStore) {
+    store.clear();
+    // Only set_rows() stores anything under this key, always as a boxed Any.
+    #[allow(unsafe_code)]
+    unsafe {
+        store.steal_data::<Box<dyn Any>>(ROWS_KEY);
+    }
+}
+
+/// Helper trait for [`ListStore`][ListStore].
+///
+/// The `rows` property binds a list of [`TreeRow`][TreeRow]s to the store.
+/// When it changes, rows are matched up with the previous list by their keys,
+/// and the store only gets the inserts, removals, moves and updates it takes
+/// to catch up, so the selection and scroll position of a view showing it
+/// survive.
+///
+/// This only works if the store is changed through `rows` and nothing else:
+/// an edit made in a view should be sent back as a message which updates the
+/// list, rather than written to the store directly. If rows have been added
+/// to or removed from the store behind its back, so it no longer has as many
+/// rows as were last bound to it, it's cleared and filled again from the new
+/// list instead.
+///
+/// A [`TreeView`][TreeView] takes a model and its columns as children, and a
+/// [`TreeViewColumn`][TreeViewColumn] takes its cell renderers, which show
+/// the store's columns through the `TreeViewColumn::attributes` child
+/// property. Rows are in the same order as the list they came from, so the
+/// first index of a [`TreePath`][TreePath] is the row's index in the list.
+///
+/// ```rust,no_run
+/// # use vgtk::{ext::*, gtk, types::TreeRow, Component, VNode};
+/// # use vgtk::lib::glib::{StaticType, ToValue, Type, Value};
+/// # use vgtk::lib::gtk::*;
+/// #[derive(Clone, PartialEq)]
+/// struct Person {
+///     id: u32,
+///     name: String,
+/// }
+///
+/// impl TreeRow for Person {
+///     type Key = u32;
+///
+///     fn column_types() -> Vec<Type> {
+///         vec![String::static_type()]
+///     }
+///
+///     fn key(&self) -> u32 {
+///         self.id
+///     }
+///
+///     fn values(&self) -> Vec<Value> {
+///         vec![self.name.to_value()]
+///     }
+/// }
+///
+/// # #[derive(Clone, Debug)] enum Message { Open(u32), Rename(usize, String) }
+/// # #[derive(Default)] struct People { people: Vec<Person> }
+/// # impl Component for People { type Message = Message; type Properties = ();
+/// # fn view(&self) -> VNode<Self> {
+/// gtk! {
+///     <TreeView on row_activated = |view, path, _| {
+///         Message::Open(view.get_row::<Person>(path).map_or(0, |person| person.id))
+///     }>
+///         <ListStore::new(&Person::column_types()) rows=self.people.clone() />
+///         <TreeViewColumn title="Name" expand=true>
+///             <CellRendererText property_editable=true TreeViewColumn::attributes=vec![("text", 0)]
+///                 on edited = |_, path, name| {
+///                     Message::Rename(path.get_indices()[0] as usize, name.to_string())
+///                 } />
+///         </TreeViewColumn>
+///     </TreeView>
+/// }
+/// # }}
+/// ```
+///
+/// [ListStore]: ../../gtk/struct.ListStore.html
+/// [TreeRow]: ../types/trait.TreeRow.html
+/// [TreeView]: ../../gtk/struct.TreeView.html
+/// [TreeViewColumn]: ../../gtk/struct.TreeViewColumn.html
+/// [TreePath]: ../../gtk/struct.TreePath.html
+pub trait ListStoreExtHelpers:
+    GtkListStoreExt + GtkListStoreExtManual + TreeModelExt + ObjectExt
+{
+    fn get_rows<R: TreeRow>(&self) -> Vec<R> {
+        with_rows(self, <[R]>::to_vec)
+    }
+
+    fn set_rows<R: TreeRow>(&self, rows: &[R]) {
+        let mut wanted = HashMap::with_capacity(rows.len());
+        for (index, row) in rows.iter().enumerate() {
+            if wanted.insert(row.key(), index).is_some() {
+                panic!("ListStore rows must have unique keys, but a key was found twice.");
+            }
+        }
+        let set_values = |iter: &TreeIter, row: &R| {
+            let values = row.values();
+            let columns: Vec<u32> = (0..values.len() as u32).collect();
+            let values: Vec<&dyn ToValue> =
+                values.iter().map(|value| value as &dyn ToValue).collect();
+            self.set(iter, &columns, &values);
+        };
+        with_rows(self, |old: &[R]| {
+            // Rows are matched up by their positions in the old list, which
+            // can't be trusted once the store has changed size without us.
+            if self.iter_n_children(None) as usize != old.len() {
+                self.clear();
+                for row in rows {
+                    set_values(&self.append(), row);
+                }
+                return;
+            }
+
+            // Remove the rows which have gone away, from the end so the
+            // positions of the others stay put.
+            for (position, row) in old.iter().enumerate().rev() {
+                if !wanted.contains_key(&row.key()) {
+                    if let Some(iter) = self.iter_nth_child(None, position as i32) {
+                        self.remove(&iter);
+                    }
+                }
+            }
+
+            // A ListStore's iters stay valid while other rows come and go, so
+            // find each remaining row's iter while they're still in order.
+            let mut kept = HashMap::new();
+            let mut order = Vec::new();
+            let mut iter = self.get_iter_first();
+            for row in old.iter().filter(|row| wanted.contains_key(&row.key())) {
+                let current = match iter {
+                    Some(current) => current,
+                    None => break,
+                };
+                let index = wanted[&row.key()];
+                iter = Some(current.clone()).filter(|next| self.iter_next(next));
+                kept.insert(index, (current, row));
+                order.push(index);
+            }
+
+            // Leave the longest run of rows which are already in order where
+            // they are, and move or insert everything else after its new
+            // predecessor.
+            let stay = longest_increasing_subsequence(&order);
+            let mut previous: Option<TreeIter> = None;
+            for (index, row) in rows.iter().enumerate() {
+                let (iter, changed) = match kept.remove(&index) {
+                    Some((iter, old)) => {
+                        if !stay.contains(&index) {
+                            self.move_after(&iter, previous.as_ref());
+                        }
+                        (iter, old != row)
+                    }
+                    None => (self.insert_after(previous.as_ref()), true),
+                };
+                if changed {
+                    set_values(&iter, row);
+                }
+                previous = Some(iter);
+            }
+        });
+        let rows: Box<dyn Any> = Box::new(rows.to_vec());
+        #[allow(unsafe_code)]
+        unsafe {
+            self.set_data(ROWS_KEY, rows);
+        }
+    }
+}
+
+impl<A> ListStoreExtHelpers for A where
+    A: GtkListStoreExt + GtkListStoreExtManual + TreeModelExt + ObjectExt
+{
+}
+
+// Marks a gio::MenuItem which has changed since it was last copied into its
+// menu.
+const MENU_ITEM_CHANGED_KEY: &str = "vgtk-menu-item-changed";
+// Marks a gio::MenuItem whose child menu is a section rather than a submenu.
+const MENU_ITEM_SECTION_KEY: &str = "vgtk-menu-item-section";
+
+/// Note that a menu item has changed, and has to be copied into its menu
+/// again.
+pub(crate) fn mark_menu_item_changed(item: &gio::MenuItem) {
+    #[allow(unsafe_code)]
+    unsafe {
+        item.set_data(MENU_ITEM_CHANGED_KEY, true);
+    }
+}
+
+/// Check whether a menu item has changed since this was last asked.
+pub(crate) fn take_menu_item_changed(item: &gio::MenuItem) -> bool {
+    #[allow(unsafe_code)]
+    let changed = unsafe { item.steal_data::<bool>(MENU_ITEM_CHANGED_KEY) };
+    changed.is_some()
+}
+
+fn is_section(item: &gio::MenuItem) -> bool {
+    #[allow(unsafe_code)]
+    let section = unsafe { item.get_data::<bool>(MENU_ITEM_SECTION_KEY) };
+    section.copied().unwrap_or(false)
+}
+
+/// Link a menu to a menu item, as a section or a submenu according to its
+/// `is_section` property, or unlink it.
+pub(crate) fn link_menu_item(item: &gio::MenuItem, menu: Option<&MenuModel>) {
+    if is_section(item) {
+        item.set_section(menu);
+    } else {
+        item.set_submenu(menu);
+    }
+    mark_menu_item_changed(item);
+}
+
+fn menu_item_string(item: &gio::MenuItem, attribute: &str) -> Option<String> {
+    item.get_attribute_value(attribute, Some(&String::static_variant_type()))
+        .and_then(|value| value.get::<String>())
+}
+
+fn set_menu_item_string(item: &gio::MenuItem, attribute: &str, value: &str) {
+    item.set_attribute_value(attribute, Some(&value.to_variant()));
+}
+
+/// Helper trait for [`gio::MenuItem`][MenuItem].
+///
+/// A [`gio::Menu`][Menu] in the [`gtk!`][gtk!] macro takes `gio::MenuItem`s
+/// as children, and other menus, which become sections without a label. A
+/// `gio::MenuItem` can take a menu as its child, which becomes its submenu,
+/// or its section if its `is_section` property is true. Items are patched in
+/// place when their properties change, so a menu can be rendered from your
+/// component's state like any other widget, and put in a
+/// [`MenuButton`][MenuButton], a [`Popover`][Popover], a `gtk::Menu` or an
+/// `Application`, which makes it the menu bar.
+///
+/// Besides `label`, items have the `action`, `target`, `icon_name`, `accel`
+/// and `hidden_when` properties, and `attributes` for any other attributes.
+///
+/// ```rust,no_run
+/// # #![recursion_limit="256"]
+/// # use vgtk::{gtk, VNode};
+/// # use vgtk::ext::*;
+/// # use vgtk::lib::gio::{Menu as GioMenu, MenuItem as GioMenuItem};
+/// # use vgtk::lib::glib::ToVariant;
+/// # use vgtk::lib::gtk::*;
+/// # fn view(recent: &[String]) -> VNode<()> {
+/// gtk! {
+///     <MenuButton>
+///         <GioMenu>
+///             <GioMenuItem label="Open…" action="win.open" accel="<Ctrl>o" icon_name="document-open" />
+///             <GioMenuItem label="Recent Files">
+///                 <GioMenu>
+///                     {
+///                         recent.iter().map(|path| gtk! {
+///                             <GioMenuItem key=path.clone() label=path.clone() action="win.open-recent"
+///                                 target=path.to_variant() />
+///                         })
+///                     }
+///                 </GioMenu>
+///             </GioMenuItem>
+///             <GioMenuItem is_section=true>
+///                 <GioMenu>
+///                     <GioMenuItem label="Quit" action="app.quit" hidden_when="action-missing" />
+///                 </GioMenu>
+///             </GioMenuItem>
+///         </GioMenu>
+///     </MenuButton>
+/// }
+/// # }
+/// ```
+///
+/// [gtk!]: ../macro.gtk.html
+/// [Menu]: ../../gio/struct.Menu.html
+/// [MenuItem]: ../../gio/struct.MenuItem.html
+/// [MenuButton]: ../../gtk/struct.MenuButton.html
+/// [Popover]: ../../gtk/struct.Popover.html
+pub trait GioMenuItemHelpers {
+    fn get_label(&self) -> Option<GString>;
+
+    fn get_detailed_action(&self) -> String;
+
+    fn get_action(&self) -> String;
+    fn set_action(&self, action: &str);
+
+    fn get_target(&self) -> Option<Variant>;
+    fn set_target(&self, target: &Variant);
+
+    fn get_icon_name(&self) -> String;
+    fn set_icon_name(&self, icon_name: &str);
+
+    fn get_accel(&self) -> String;
+    fn set_accel(&self, accel: &str);
+
+    fn get_hidden_when(&self) -> String;
+    fn set_hidden_when(&self, hidden_when: &str);
+
+    fn get_attributes(&self) -> Vec<(&'static str, Variant)>;
+    fn set_attributes(&self, attributes: &[(&str, Variant)]);
+
+    fn get_is_section(&self) -> bool;
+    fn set_is_section(&self, is_section: bool);
+}
+
+impl GioMenuItemHelpers for gio::MenuItem {
+    fn get_label(&self) -> Option<GString> {
+        menu_item_string(self, "label").map(GString::from)
+    }
+
+    fn get_detailed_action(&self) -> String {
+        self.get_action()
+    }
+
+    fn get_action(&self) -> String {
+        menu_item_string(self, "action").unwrap_or_default()
+    }
+
+    fn set_action(&self, action: &str) {
+        set_menu_item_string(self, "action", action);
+    }
+
+    fn get_target(&self) -> Option<Variant> {
+        self.get_attribute_value("target", None)
+    }
+
+    fn set_target(&self, target: &Variant) {
+        self.set_attribute_value("target", Some(target));
+    }
+
+    fn get_icon_name(&self) -> String {
+        // Icons are stored serialised, so it's always set.
+        String::new()
+    }
+
+    fn set_icon_name(&self, icon_name: &str) {
+        self.set_icon(&ThemedIcon::new(icon_name));
+    }
+
+    fn get_accel(&self) -> String {
+        menu_item_string(self, "accel").unwrap_or_default()
+    }
+
+    fn set_accel(&self, accel: &str) {
+        set_menu_item_string(self, "accel", accel);
+    }
+
+    fn get_hidden_when(&self) -> String {
+        menu_item_string(self, "hidden-when").unwrap_or_default()
+    }
+
+    fn set_hidden_when(&self, hidden_when: &str) {
+        set_menu_item_string(self, "hidden-when", hidden_when);
+    }
+
+    fn get_attributes(&self) -> Vec<(&'static str, Variant)> {
+        // There's no way to list a menu item's attributes, so they're always
+        // set.
+        Vec::new()
+    }
+
+    fn set_attributes(&self, attributes: &[(&str, Variant)]) {
+        for (attribute, value) in attributes {
+            self.set_attribute_value(attribute, Some(value));
+        }
+    }
+
+    fn get_is_section(&self) -> bool {
+        is_section(self)
+    }
+
+    fn set_is_section(&self, section: bool) {
+        if is_section(self) == section {
+            return;
+        }
+        let (from, to) = if section {
+            ("submenu", "section")
+        } else {
+            ("section", "submenu")
+        };
+        let menu = self.get_link(from);
+        #[allow(unsafe_code)]
+        unsafe {
+            self.set_data(MENU_ITEM_SECTION_KEY, section);
+        }
+        if let Some(menu) = menu {
+            self.set_link(from, None::<&MenuModel>);
+            self.set_link(to, Some(&menu));
+            mark_menu_item_changed(self);
+        }
+    }
+}
+
 /// Helper trait for [`Notebook`][Notebook].
 ///
 /// [Notebook]: ../../gtk/struct.Notebook.html
@@ -249,6 +827,257 @@ pub trait NotebookExtHelpers: NotebookExt {
 
 impl<A> NotebookExtHelpers for A where A: NotebookExt {}
 
+/// Helper trait for [`Overlay`][Overlay].
+///
+/// [Overlay]: ../../gtk/struct.Overlay.html
+pub trait OverlayExtHelpers: OverlayExt {
+    fn get_child_pass_through<P: IsA<Widget>>(&self, child: &P) -> bool {
+        self.get_overlay_pass_through(child)
+    }
+
+    fn set_child_pass_through<P: IsA<Widget>>(&self, child: &P, pass_through: bool) {
+        self.set_overlay_pass_through(child, pass_through);
+    }
+}
+
+impl<A> OverlayExtHelpers for A where A: OverlayExt {}
+
+/// Helper trait for [`SimpleAction`][SimpleAction].
+///
+/// An action's name, parameter type and state type are fixed when it's made,
+/// so if a render asks for an action which differs from the existing one in
+/// any of them, the action gets rebuilt. These constructors take the state
+/// type from the initial state's Rust type. The `state` and `enabled`
+/// properties are patched like any other property.
+///
+/// The `activate_value` and `change_state_value` signals are the `activate`
+/// and `change_state` signals with their parameter converted from a
+/// [`Variant`][Variant] to the type the handler asks for.
+///
+/// ```rust,no_run
+/// # use vgtk::{gtk, Component, UpdateAction, VNode};
+/// # use vgtk::ext::*;
+/// # use vgtk::lib::gio::{prelude::*, SimpleAction};
+/// # use vgtk::lib::glib::{StaticVariantType, ToVariant};
+/// # use vgtk::lib::gtk::*;
+/// # #[derive(Clone, Debug)]
+/// # enum Message { Dark(bool), Zoom(i32) }
+/// # #[derive(Default)]
+/// # struct Viewer { dark: bool, zoom: i32 }
+/// # impl Component for Viewer {
+/// # type Message = Message;
+/// # type Properties = ();
+/// # fn view(&self) -> VNode<Self> {
+/// gtk! {
+///     <Box orientation=Orientation::Vertical>
+///         <SimpleAction::with_state("dark", &false) state=self.dark.to_variant()
+///             on change_state_value = |_, dark| Message::Dark(dark) />
+///         <SimpleAction::new("zoom", Some(&i32::static_variant_type())) enabled=(self.zoom < 400)
+///             Widget::action_prefix="viewer" on activate_value = |_, step| Message::Zoom(step) />
+///         <CheckButton label="Dark" action_name="widget.dark" />
+///         <Button label="Zoom in" action_name="viewer.zoom" action_target_value=10.to_variant() />
+///     </Box>
+/// }
+/// # }
+/// # }
+/// ```
+///
+/// [SimpleAction]: ../../gio/struct.SimpleAction.html
+/// [Variant]: ../../glib/variant/struct.Variant.html
+pub trait SimpleActionHelpers {
+    /// Construct an action with a state, which takes no parameter.
+    fn with_state<S: ToVariant>(name: &str, state: &S) -> SimpleAction {
+        SimpleAction::new_stateful(name, None, &state.to_variant())
+    }
+
+    /// Construct an action with a state, which takes a parameter of the same
+    /// type as the state and asks for its state to be changed to it, like a
+    /// group of radio buttons does.
+    fn with_state_parameter<S: StaticVariantType + ToVariant>(
+        name: &str,
+        state: &S,
+    ) -> SimpleAction {
+        SimpleAction::new_stateful(name, Some(&S::static_variant_type()), &state.to_variant())
+    }
+
+    fn connect_activate_value<T: FromVariant, F: Fn(&SimpleAction, T) + 'static>(
+        &self,
+        f: F,
+    ) -> SignalHandlerId;
+
+    fn connect_change_state_value<T: FromVariant, F: Fn(&SimpleAction, T) + 'static>(
+        &self,
+        f: F,
+    ) -> SignalHandlerId;
+}
+
+impl SimpleActionHelpers for SimpleAction {
+    fn connect_activate_value<T: FromVariant, F: Fn(&SimpleAction, T) + 'static>(
+        &self,
+        f: F,
+    ) -> SignalHandlerId {
+        self.connect_activate(move |action, parameter| {
+            // GIO has already checked the parameter's type against the
+            // action's, so this only skips a handler asking for the wrong one.
+            match parameter.and_then(Variant::get) {
+                Some(value) => f(action, value),
+                None => trace!("{} {:?}", "Unexpected parameter:".bright_red(), parameter),
+            }
+        })
+    }
+
+    fn connect_change_state_value<T: FromVariant, F: Fn(&SimpleAction, T) + 'static>(
+        &self,
+        f: F,
+    ) -> SignalHandlerId {
+        self.connect_change_state(move |action, state| match state.and_then(Variant::get) {
+            Some(value) => f(action, value),
+            None => trace!("{} {:?}", "Unexpected state:".bright_red(), state),
+        })
+    }
+}
+
+/// Helper trait for [`TreeView`][TreeView].
+///
+/// [TreeView]: ../../gtk/struct.TreeView.html
+pub trait TreeViewExtHelpers: TreeViewExt {
+    /// Get the row at `path`, if the view's model is a
+    /// [`ListStore`][ListStore] bound to rows of this type through its `rows`
+    /// property.
+    ///
+    /// [ListStore]: ../../gtk/struct.ListStore.html
+    fn get_row<R: TreeRow>(&self, path: &TreePath) -> Option<R> {
+        let model = self.get_model()?;
+        let index = *path.get_indices().first()?;
+        with_rows(&model, |rows: &[R]| rows.get(index as usize).cloned())
+    }
+}
+
+impl<A> TreeViewExtHelpers for A where A: TreeViewExt {}
+
+/// Helper trait for [`TreeViewColumn`][TreeViewColumn].
+///
+/// The `attributes` child property of a cell renderer lists which of the
+/// model's columns each of the renderer's properties shows, as
+/// `(property, column)` pairs.
+///
+/// [TreeViewColumn]: ../../gtk/struct.TreeViewColumn.html
+pub trait TreeViewColumnExtHelpers: TreeViewColumnExt + CellLayoutExt {
+    fn get_child_attributes<P: IsA<CellRenderer>>(&self, _child: &P) -> Vec<(&'static str, i32)> {
+        // GTK has no way to read them back, so they're always set.
+        Vec::new()
+    }
+
+    fn set_child_attributes<P: IsA<CellRenderer>>(&self, child: &P, attributes: &[(&str, i32)]) {
+        self.clear_attributes(child);
+        for (attribute, column) in attributes {
+            self.add_attribute(child, attribute, *column);
+        }
+    }
+
+    fn get_child_expand<P: IsA<CellRenderer>>(&self, _child: &P) -> bool {
+        // Always compare true, it's all taken care of in add_child().
+        true
+    }
+
+    fn set_child_expand<P: IsA<CellRenderer>>(&self, _child: &P, _expand: bool) {
+        // This is handled by add_child() rules. The setter is a no-op.
+    }
+}
+
+impl<A> TreeViewColumnExtHelpers for A where A: TreeViewColumnExt + CellLayoutExt {}
+
+/// The prefix a widget's actions go under when they don't have an
+/// `action_prefix` child property.
+pub(crate) const DEFAULT_ACTION_PREFIX: &str = "widget";
+
+// Where an action added to a widget remembers the prefix it went under.
+const ACTION_PREFIX_KEY: &str = "vgtk-action-prefix";
+
+// Where a widget keeps the action group for a prefix.
+fn action_group_key(prefix: &str) -> String {
+    format!("vgtk-actions-{}", prefix)
+}
+
+// The prefix an action was added to a widget under, if it was.
+fn widget_action_prefix(action: &Action) -> Option<String> {
+    #[allow(unsafe_code)]
+    let prefix = unsafe { action.get_data::<String>(ACTION_PREFIX_KEY) };
+    prefix.cloned()
+}
+
+/// Add an action to the group a widget keeps for `prefix`, making the group
+/// and inserting it into the widget first if it doesn't have one yet.
+pub(crate) fn add_widget_action(widget: &Widget, action: &Action, prefix: &str) {
+    let key = action_group_key(prefix);
+    // Only this function stores anything under these keys.
+    #[allow(unsafe_code)]
+    let group = unsafe { widget.get_data::<SimpleActionGroup>(&key) }.cloned();
+    let group = group.unwrap_or_else(|| {
+        let group = SimpleActionGroup::new();
+        widget.insert_action_group(prefix, Some(&group));
+        #[allow(unsafe_code)]
+        unsafe {
+            widget.set_data(&key, group.clone());
+        }
+        group
+    });
+    group.add_action(action);
+    #[allow(unsafe_code)]
+    unsafe {
+        action.set_data(ACTION_PREFIX_KEY, prefix.to_string());
+    }
+}
+
+/// Take an action out of the group it was added to with
+/// [`add_widget_action()`][add_widget_action], returning false if it wasn't
+/// added that way.
+pub(crate) fn remove_widget_action(widget: &Widget, action: &Action) -> bool {
+    let prefix = match widget_action_prefix(action) {
+        Some(prefix) => prefix,
+        None => return false,
+    };
+    let name = action.get_name().expect("Action unexpectedly has no name");
+    #[allow(unsafe_code)]
+    let group = unsafe { widget.get_data::<SimpleActionGroup>(&action_group_key(&prefix)) };
+    if let Some(group) = group {
+        group.remove_action(&name);
+    }
+    #[allow(unsafe_code)]
+    unsafe {
+        action.steal_data::<String>(ACTION_PREFIX_KEY);
+    }
+    true
+}
+
+/// Helper trait for [`Widget`][Widget].
+///
+/// Any widget can take [`Action`][Action]s as children. Except for an
+/// [`ApplicationWindow`][ApplicationWindow]'s, which it keeps under the `win`
+/// prefix, they go in an action group the widget keeps for the prefix in
+/// their `action_prefix` child property, or `widget` if they don't have one,
+/// and the widget and everything inside it can activate them, for example
+/// through an `action_name` of `"widget.open"`.
+///
+/// [Widget]: ../../gtk/struct.Widget.html
+/// [Action]: ../../gio/struct.Action.html
+/// [ApplicationWindow]: ../../gtk/struct.ApplicationWindow.html
+pub trait WidgetExtHelpers: IsA<Widget> {
+    fn get_child_action_prefix<P: IsA<Action>>(&self, action: &P) -> String {
+        widget_action_prefix(action.as_ref()).unwrap_or_else(|| DEFAULT_ACTION_PREFIX.to_string())
+    }
+
+    fn set_child_action_prefix<P: IsA<Action>>(&self, action: &P, prefix: &str) {
+        if widget_action_prefix(action.as_ref()).as_deref() != Some(prefix) {
+            let widget: &Widget = self.upcast_ref();
+            remove_widget_action(widget, action.as_ref());
+            add_widget_action(widget, action.as_ref(), prefix);
+        }
+    }
+}
+
+impl<A> WidgetExtHelpers for A where A: IsA<Widget> {}
+
 /// Helper trait for [`Grid`][Grid] layout.
 ///
 /// This helper enables using the GTK Grid for laying out widgets in a window.
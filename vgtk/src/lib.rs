@@ -125,7 +125,12 @@
 //! [`Defer`][UpdateAction::Defer] with a [`Future`][Future] in case you need to
 //! do some I/O or a similar asynchronous task - the [`Future`][Future] should resolve to a
 //! [`Component::Message`][Component::Message] which will be passed along to [`update`][Component::update]
-//! when the [`Future`][Future] resolves.
+//! when the [`Future`][Future] resolves. If the component unmounts first, the [`Future`][Future] is
+//! dropped instead. There are a few more actions, like [`Stream`][UpdateAction::Stream] for a stream
+//! of messages and [`Parent`][UpdateAction::Parent] for sending a message to the parent component,
+//! and several can be performed at once with [`Batch`][UpdateAction::Batch]. Work which would keep
+//! the UI busy for too long can be run on a background thread with
+//! [`Background`][UpdateAction::Background].
 //!
 //! ## Signal Handlers
 //!
@@ -184,6 +189,11 @@
 //! # }}
 //! ```
 //!
+//! Messages which come from outside the UI, like the ticks of a timer or changes to a file, can be
+//! subscribed to by implementing [`Component::subscriptions`][Component::subscriptions]. It returns
+//! a list of [`Subscription`][Subscription]s which the framework starts and stops as the list
+//! changes from one update to the next, and stops entirely when the component unmounts.
+//!
 //! ## The `gtk!` Syntax
 //!
 //! The syntax for the [`gtk!`][vgtk::gtk!] macro is similar to [JSX], but with a number of necessary
@@ -255,6 +265,85 @@
 //! # }
 //! ```
 //!
+//! A few child properties are faked by [`vgtk::ext`][vgtk::ext] to put a child in a special place
+//! in its parent, such as `Box::center_widget`, `HeaderBar::custom_title` and `Window::titlebar`.
+//! A child can move in and out of these places between renders:
+//!
+//! ```rust,no_run
+//! # use vgtk::{gtk, gtk_if, VNode, ext::*};
+//! # use vgtk::lib::gtk::{Window, HeaderBar, HeaderBarExt, Label, LabelExt};
+//! # fn view(fullscreen: bool) -> VNode<()> {
+//! gtk! {
+//!     <Window>
+//!         {
+//!             gtk_if!(!fullscreen => {
+//!                 <HeaderBar title="Slides" Window::titlebar=true />
+//!             })
+//!         }
+//!         <Label label="Slide 1" />
+//!     </Window>
+//! }
+//! # }
+//! ```
+//!
+//! The pages of a [`Stack`][Stack] are named and titled with the `Stack::name` and `Stack::title`
+//! child properties, and its `visible_child_name` is set once its pages are in place, so the
+//! visible page can follow your component's state. Point a `StackSwitcher` or `StackSidebar` at
+//! the [`Stack`][Stack] through its `stack` property to let the user pick a page as well.
+//!
+//! ```rust,no_run
+//! # use vgtk::{gtk, VNode};
+//! # use vgtk::lib::gtk::{Stack, StackExt, Label, LabelExt};
+//! # fn view(page: &str) -> VNode<()> {
+//! gtk! {
+//!     <Stack visible_child_name=page>
+//!         <Label label="Introduction" Stack::name="intro" Stack::title="Introduction" />
+//!         <Label label="Settings" Stack::name="settings" Stack::title="Settings" />
+//!     </Stack>
+//! }
+//! # }
+//! ```
+//!
+//! A [`Paned`][Paned] packs its first child on one side and its second on the other, using the
+//! `Paned::resize` and `Paned::shrink` child properties. An [`Overlay`][Overlay] shows its first
+//! child and lays the rest on top of it, ordered by `Overlay::index`, which can let input through
+//! to the widgets below with `Overlay::pass_through`. A [`Popover`][Popover] isn't put inside its
+//! parent, but points at it, and stays hidden until you set its `visible` property:
+//!
+//! ```rust,no_run
+//! # use vgtk::{gtk, VNode};
+//! # use vgtk::lib::gtk::{Button, ButtonExt, Label, LabelExt, Popover, WidgetExt};
+//! # fn view(open: bool) -> VNode<()> {
+//! gtk! {
+//!     <Button label="Details">
+//!         <Popover visible=open>
+//!             <Label label="Here are the details." />
+//!         </Popover>
+//!     </Button>
+//! }
+//! # }
+//! ```
+//!
+//! A [`TreeView`][TreeView] takes its model and its columns as children, and each column takes
+//! its cell renderers. A `ListStore` model can be bound to a list of rows from your component with
+//! its `rows` property, which only touches the rows that changed; see
+//! [`ListStoreExtHelpers`][ListStoreExtHelpers] for how it fits together.
+//!
+//! Rendering a row for each of thousands of items as children of a `ListBox` builds every row
+//! up front and diffs them all on every render. Put a [`VirtualList`][VirtualList] in the
+//! `ListBox` or `FlowBox` instead, and it binds the container to a model of your items, building
+//! rows from a function you give it as the container asks for them.
+//!
+//! Actions can be children too. An `Application` or `ApplicationWindow` keeps them under the
+//! `app` or `win` prefix, and any other widget keeps them in an action group of its own; see
+//! [`SimpleActionHelpers`][SimpleActionHelpers] for actions with parameters and state. Their
+//! `accels` child properties bind keyboard shortcuts through the application's
+//! [`Shortcuts`][Shortcuts] registry.
+//!
+//! Menus built from `gio::Menu` and `gio::MenuItem` nodes are patched like everything else, and
+//! can go in a `MenuButton`, a `Popover` or an `Application`'s menu bar; see
+//! [`GioMenuItemHelpers`][GioMenuItemHelpers].
+//!
 //! The final addition to the attribute syntax pertains to when you need to qualify an
 //! ambiguous method name. For instance, a [`MenuButton`][MenuButton] implements both
 //! [`WidgetExt`][WidgetExt] and [`MenuButtonExt`][MenuButtonExt], both of which contains
@@ -271,6 +360,11 @@
 //! # }}
 //! ```
 //!
+//! When patching, a property is only set if its value differs from the one set by the
+//! previous render. For a property the user can change, like the text of an `Entry`, wrap
+//! the value in [`properties::live()`][live] to compare it with the widget's current value
+//! instead.
+//!
 //! ### Interpolation
 //!
 //! The `gtk!` macro's parser tries to be smart about recognising Rust expressions as attribute
@@ -299,6 +393,67 @@
 //! # }
 //! ```
 //!
+//! ### Keys
+//!
+//! By default, the differ matches up children with the previous render by their position.
+//! If you're rendering a list where items can be inserted, removed or reordered, you should
+//! give each child a `key` attribute which uniquely identifies it among its siblings. Keyed
+//! children are matched by key instead, so existing widgets (and their focus, scroll position
+//! and any other internal state) are moved rather than rebuilt, and only the children which
+//! actually appeared or disappeared are constructed or destroyed.
+//!
+//! The key can be any value which implements [`ToString`][ToString], and it works on both
+//! widgets and subcomponents.
+//!
+//! ```rust,no_run
+//! # use vgtk::{gtk, VNode};
+//! # use vgtk::lib::gtk::{Label, LabelExt, ListBox};
+//! # struct Item { id: usize, name: String }
+//! # fn view(items: &[Item]) -> VNode<()> {
+//! gtk! {
+//!     <ListBox>
+//!         {
+//!             items.iter().map(|item| gtk! {
+//!                 <Label key=item.id label=item.name.clone() />
+//!             })
+//!         }
+//!     </ListBox>
+//! }
+//! # }
+//! ```
+//!
+//! ### Memoisation
+//!
+//! Every render patches the whole widget tree against the new view. If a large part of it
+//! only depends on a few values, you can wrap it in a `<memo>` element with a `deps`
+//! attribute, and the differ will leave it alone as long as `deps` is equal to what it was
+//! the last time it was patched. The dependencies can be any value (or tuple of values)
+//! which implements [`PartialEq`][PartialEq]. They're moved into the virtual tree, so
+//! you'll need to clone anything you've only borrowed.
+//!
+//! Everything inside the memo, including the values captured by its signal handlers, should
+//! depend only on `deps`, or it won't be updated when it changes. A `<memo>` element takes
+//! exactly one child.
+//!
+//! ```rust,no_run
+//! # use vgtk::{gtk, VNode};
+//! # use vgtk::lib::gtk::{Box, Label, LabelExt, ListBox};
+//! # fn view(status: &str, items: &Vec<String>) -> VNode<()> {
+//! gtk! {
+//!     <Box>
+//!         <Label label=status />
+//!         <memo deps=items.clone()>
+//!             <ListBox>
+//!                 {
+//!                     items.iter().map(|item| gtk! { <Label label=item.clone() /> })
+//!                 }
+//!             </ListBox>
+//!         </memo>
+//!     </Box>
+//! }
+//! # }
+//! ```
+//!
 //! ## Subcomponents
 //!
 //! Components are designed to be composable, so you can place one component inside
@@ -321,6 +476,11 @@
 //! Subcomponents do *not* support signal handlers, because a component is not a GTK object. You'll have
 //! to use the [`Callback`][Callback] type to communicate between a subcomponent and its parent.
 //!
+//! A subcomponent's [`view`][Component::view] doesn't have to return a single widget. If it returns
+//! a [`VNode::Fragment`][VNode::Fragment], each node in the fragment is added to the parent's container as if
+//! the parent had rendered it in place of the subcomponent, and [`VNode::Empty`][VNode::Empty] adds nothing
+//! at all. This is handy for a component which renders a group of `ListBox` rows, say.
+//!
 //! This is what a very simple button subcomponent might look like:
 //!
 //! ```rust,no_run
@@ -458,11 +618,18 @@
 //! [Component::change]: trait.Component.html#method.change
 //! [Component::Message]: trait.Component.html#associatedtype.Message
 //! [Component::Properties]: trait.Component.html#associatedtype.Properties
+//! [Component::subscriptions]: trait.Component.html#method.subscriptions
 //! [Callback]: struct.Callback.html
 //! [UpdateAction]: enum.UpdateAction.html
 //! [UpdateAction::None]: enum.UpdateAction.html#variant.None
 //! [UpdateAction::Render]: enum.UpdateAction.html#variant.Render
 //! [UpdateAction::Defer]: enum.UpdateAction.html#variant.Defer
+//! [UpdateAction::Stream]: enum.UpdateAction.html#variant.Stream
+//! [UpdateAction::Parent]: enum.UpdateAction.html#variant.Parent
+//! [UpdateAction::Batch]: enum.UpdateAction.html#variant.Batch
+//! [UpdateAction::Background]: enum.UpdateAction.html#variant.Background
+//! [VNode::Fragment]: enum.VNode.html#variant.Fragment
+//! [VNode::Empty]: enum.VNode.html#variant.Empty
 //! [Application]: ../gtk/struct.Application.html
 //! [Buildable]: ../gtk/struct.Buildable.html
 //! [Button]: ../gtk/struct.Button.html
@@ -473,25 +640,44 @@
 //! [Container]: ../gtk/struct.Container.html
 //! [MenuButton]: ../gtk/struct.MenuButton.html
 //! [MenuButtonExt]: ../gtk/trait.MenuButtonExt.html
+//! [Overlay]: ../gtk/struct.Overlay.html
+//! [Paned]: ../gtk/struct.Paned.html
+//! [Popover]: ../gtk/struct.Popover.html
+//! [Stack]: ../gtk/struct.Stack.html
+//! [TreeView]: ../gtk/struct.TreeView.html
+//! [ListStoreExtHelpers]: ext/trait.ListStoreExtHelpers.html
+//! [VirtualList]: struct.VirtualList.html
+//! [SimpleActionHelpers]: ext/trait.SimpleActionHelpers.html
+//! [Shortcuts]: struct.Shortcuts.html
+//! [Subscription]: struct.Subscription.html
+//! [GioMenuItemHelpers]: ext/trait.GioMenuItemHelpers.html
 //! [WidgetExt]: ../gtk/trait.WidgetExt.html
 //! [Window]: ../gtk/struct.Window.html
 //! [Future]: https://doc.rust-lang.org/std/future/trait.Future.html
+//! [ToString]: https://doc.rust-lang.org/std/string/trait.ToString.html
+//! [PartialEq]: https://doc.rust-lang.org/std/cmp/trait.PartialEq.html
+//! [live]: properties/fn.live.html
 
 #![forbid(rust_2018_idioms)]
 #![deny(nonstandard_style, unsafe_code)]
 #![warn(unreachable_pub, missing_docs)]
 #![allow(clippy::needless_doctest_main)]
 
+mod background;
 mod callback;
 mod component;
 pub mod ext;
 mod menu_builder;
 #[doc(hidden)]
 pub mod properties;
+mod scheduler;
 #[doc(hidden)]
 pub mod scope;
+mod shortcuts;
+mod subscription;
 pub mod types;
 mod vdom;
+mod virtual_list;
 #[doc(hidden)]
 pub mod vnode;
 
@@ -521,10 +707,14 @@ use log::debug;
 
 use crate::component::{ComponentMessage, ComponentTask, PartialComponentTask};
 
+pub use crate::background::Progress;
 pub use crate::callback::Callback;
 pub use crate::component::{current_object, current_window, Component, UpdateAction};
 pub use crate::menu_builder::{menu, MenuBuilder};
-pub use crate::scope::Scope;
+pub use crate::scope::{Scope, SendError, Sender};
+pub use crate::shortcuts::{ShortcutError, Shortcuts};
+pub use crate::subscription::Subscription;
+pub use crate::virtual_list::{RowView, VirtualList, VirtualListProperties};
 pub use crate::vnode::{VNode, VNodeIterator};
 
 /// Re-exports of GTK and its associated libraries.
@@ -586,7 +776,8 @@ pub fn run<C: 'static + Component>() -> i32 {
 ///
 /// Calling this instead of [`vgtk::run()`][run] is useful if you need to get your
 /// component's [`Scope`][Scope] in order to fire off some async work at startup and
-/// notify it when the work is done.
+/// notify it when the work is done. To notify it from another thread, use the
+/// [`Sender`][Sender] you get from [`Scope::sender()`][Scope::sender].
 ///
 /// If the component doesn't have an [`Application`][Application] as its top level
 /// object, this function will panic.
@@ -608,6 +799,8 @@ pub fn run<C: 'static + Component>() -> i32 {
 /// [Application::quit]: ../gio/trait.ApplicationExt.html#tymethod.quit
 /// [Application::run]: ../gio/trait.ApplicationExt.html#tymethod.run
 /// [Scope]: struct.Scope.html
+/// [Scope::sender]: struct.Scope.html#method.sender
+/// [Sender]: struct.Sender.html
 pub fn start<C: 'static + Component>() -> (Application, Scope<C>) {
     gtk::init().expect("GTK failed to initialise");
     let partial_task = PartialComponentTask::<C, ()>::new(Default::default(), None, None);
@@ -626,7 +819,7 @@ pub fn start<C: 'static + Component>() -> (Application, Scope<C>) {
 
     let constructor = once(AndThen::DoNothing, move |_| {
         let (channel, task) = partial_task.finalise();
-        MainContext::ref_thread_default().spawn_local(task);
+        task.spawn();
         channel.unbounded_send(ComponentMessage::Mounted).unwrap();
         const_app.connect_shutdown(move |_| {
             channel.unbounded_send(ComponentMessage::Unmounted).unwrap();
@@ -652,6 +845,27 @@ pub fn start<C: 'static + Component>() -> (Application, Scope<C>) {
 /// If the component doesn't have a [`Dialog`][Dialog] (or something which implements [`Dialog`][Dialog])
 /// as its top level object, this function will panic.
 ///
+/// The dialog's buttons are children with a `Dialog::response` child property, which puts
+/// them in the action area and makes them respond with the given [`ResponseType`][ResponseType].
+/// The dialog's `default_response` picks the button activated by the Enter key, and a
+/// button's `sensitive` property decides whether its response can be chosen at all. Both
+/// are patched like any other property.
+///
+/// ```rust,no_run
+/// # use vgtk::{ext::*, gtk, VNode};
+/// # use vgtk::lib::gtk::*;
+/// # fn view(name: &str) -> VNode<()> {
+/// gtk! {
+///     <Dialog default_response=ResponseType::Accept>
+///         <Label label=format!("Delete {}?", name) />
+///         <Button label="Cancel" Dialog::response=ResponseType::Cancel />
+///         <Button label="Delete" Dialog::response=ResponseType::Accept
+///                 sensitive=(!name.is_empty()) />
+///     </Dialog>
+/// }
+/// # }
+/// ```
+///
 /// [Dialog]: ../gtk/struct.Dialog.html
 /// [ResponseType]: ../gtk/enum.ResponseType.html
 /// [Future]: https://doc.rust-lang.org/std/future/trait.Future.html
@@ -683,7 +897,7 @@ pub fn run_dialog_props<C: 'static + Component>(
     if let Some(parent) = parent {
         dialog.set_transient_for(Some(parent));
     }
-    MainContext::ref_thread_default().spawn_local(task);
+    task.spawn();
     let (notify, result) = oneshot::channel();
     channel.unbounded_send(ComponentMessage::Mounted).unwrap();
     let resolve = once(
@@ -737,6 +951,52 @@ pub fn quit() {
         .quit();
 }
 
+/// Render every component with pending changes, right now.
+///
+/// Components don't render as soon as their [`update()`][update] asks them
+/// to. Instead, they're marked as needing it, and all of them are rendered
+/// together just before GTK draws the next frame, parents before their
+/// children. This function runs the main loop until it has nothing left to
+/// do and then renders whatever is still waiting, so that tests can check
+/// the widget tree after sending a component some messages.
+///
+/// Don't call it from inside a component.
+///
+/// ```rust,no_run
+/// # use vgtk::{ext::*, gtk, Component, UpdateAction, VNode};
+/// # use vgtk::lib::gtk::*;
+/// # use vgtk::lib::gio::{prelude::*, ApplicationFlags};
+/// # #[derive(Clone, Debug)] enum Message { Increment }
+/// # #[derive(Default)] struct Counter { count: usize }
+/// # impl Component for Counter {
+/// #     type Message = Message;
+/// #     type Properties = ();
+/// #     fn update(&mut self, _msg: Message) -> UpdateAction<Self> {
+/// #         self.count += 1;
+/// #         UpdateAction::Render
+/// #     }
+/// #     fn view(&self) -> VNode<Self> { gtk! {
+/// #         <Application::new_unwrap(None, ApplicationFlags::empty())>
+/// #             <Window>
+/// #                 <Label label=self.count.to_string() />
+/// #             </Window>
+/// #         </Application>
+/// #     } }
+/// # }
+/// let (app, scope) = vgtk::start::<Counter>();
+/// app.activate();
+/// scope.send_message(Message::Increment);
+/// vgtk::flush();
+/// // The label now reads "1".
+/// ```
+///
+/// [update]: trait.Component.html#method.update
+pub fn flush() {
+    let context = MainContext::ref_thread_default();
+    while context.iteration(false) {}
+    scheduler::flush_pending();
+}
+
 /// Connect a GLib signal to a [`Future`][Future].
 ///
 /// This macro takes a GLib object and the name of a method to connect it to a
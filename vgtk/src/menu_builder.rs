@@ -2,6 +2,10 @@ use gio::{Menu, MenuItem};
 
 /// Makes a [`gtk::Menu`][Menu] for you.
 ///
+/// The menu is built once. For a menu which follows your component's state,
+/// put a `gio::Menu` in the [`gtk!`][gtk!] macro instead; see
+/// [`GioMenuItemHelpers`][GioMenuItemHelpers].
+///
 /// # Examples
 ///
 /// ```rust,no_run
@@ -20,6 +24,8 @@ use gio::{Menu, MenuItem};
 /// ```
 ///
 /// [Menu]: https://gtk-rs.org/docs/gtk/struct.Menu.html
+/// [gtk!]: macro.gtk.html
+/// [GioMenuItemHelpers]: ext/trait.GioMenuItemHelpers.html
 pub struct MenuBuilder {
     menu: Menu,
 }
@@ -1,16 +1,31 @@
-//! Property conversion traits.
+//! Property conversion traits, and resetting properties which have been left out of a render.
 
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::marker::PhantomData;
+use std::rc::Rc;
 
 use cairo::Surface;
-use glib::{Cast, GString};
-use gtk::{IconSize, Image, ImageExt, Widget};
+use gio::MenuItem;
+use glib::translate::ToGlib;
+use glib::{Cast, GString, IsA, Object, ObjectExt, ParamFlags, StaticType, Type, Value, Variant};
+use gtk::prelude::WidgetExtManual;
+use gtk::{
+    Adjustment, CheckMenuItem, ComboBox, ComboBoxText, ComboBoxTextExt, Entry, Expander, IconSize,
+    Image, ImageExt, Label, LabelExt, ListStore, Notebook, Paned, Popover, Range, SpinButton,
+    Stack, Switch, ToggleButton, Widget, WidgetExt,
+};
+use log::debug;
+
+use crate::ext::{clear_rows, GioMenuItemHelpers};
 
 pub struct PropertyValue<'a, A, Get, Set>
 where
     A: PropertyValueCompare<'a, Get> + PropertyValueCoerce<'a, Set> + 'a,
 {
-    value: A,
+    value: Rc<A>,
+    live: bool,
     lifetime: PhantomData<&'a (Get, Set)>,
 }
 
@@ -20,7 +35,8 @@ where
 {
     pub fn new(value: A) -> Self {
         Self {
-            value,
+            value: Rc::new(value),
+            live: false,
             lifetime: PhantomData,
         }
     }
@@ -32,6 +48,34 @@ where
     pub fn coerce(&'a self) -> Set {
         A::property_coerce(&self.value)
     }
+
+    /// Whether the object's current value should be consulted before setting
+    /// this value, rather than the value set by the previous render.
+    pub fn is_live(&self) -> bool {
+        self.live
+    }
+
+    /// The value, to be compared with the next render's value.
+    pub fn cached(&self) -> Rc<dyn Any>
+    where
+        A: 'static,
+    {
+        self.value.clone()
+    }
+
+    /// A function comparing two cached values of this type.
+    pub fn cache_equal(&self) -> fn(&dyn Any, &dyn Any) -> bool
+    where
+        A: PartialEq + 'static,
+    {
+        fn equal<A: PartialEq + 'static>(left: &dyn Any, right: &dyn Any) -> bool {
+            match (left.downcast_ref::<A>(), right.downcast_ref::<A>()) {
+                (Some(left), Some(right)) => left == right,
+                _ => false,
+            }
+        }
+        equal::<A>
+    }
 }
 
 pub trait PropertyValueCompare<'a, A> {
@@ -96,6 +140,12 @@ where
     }
 }
 
+impl<'a, A> PropertyValueCoerce<'a, &'a [A]> for Vec<A> {
+    fn property_coerce(value: &'a Vec<A>) -> &'a [A] {
+        value.as_slice()
+    }
+}
+
 impl<'a, A> PropertyValueCoerce<'a, Option<&'a A>> for Option<A> {
     fn property_coerce(value: &'a Option<A>) -> Option<&'a A> {
         value.as_ref()
@@ -158,6 +208,18 @@ impl<'a> PropertyValueCoerce<'a, Option<GString>> for String {
     }
 }
 
+impl<'a> PropertyValueCompare<'a, Option<Variant>> for Variant {
+    fn property_compare(left: Option<Variant>, right: &Variant) -> bool {
+        left.as_ref() == Some(right)
+    }
+}
+
+impl<'a> PropertyValueCoerce<'a, Option<&'a Variant>> for Variant {
+    fn property_coerce(value: &'a Variant) -> Option<&'a Variant> {
+        Some(value)
+    }
+}
+
 impl<'a> PropertyValueCompare<'a, Option<Widget>> for Image {
     fn property_compare(left: Option<Widget>, right: &Image) -> bool {
         if let Some(left) = left {
@@ -176,38 +238,86 @@ impl<'a> PropertyValueCoerce<'a, Option<&'a Image>> for Image {
     }
 }
 
-impl<'a> PropertyValueCoerce<'a, Option<&'a Surface>> for Surface {
-    fn property_coerce(value: &'a Surface) -> Option<&'a Surface> {
-        Some(value)
+impl PropertyValueCompare<'_, Vec<GString>> for &'_ [&'_ str] {
+    fn property_compare(left: Vec<GString>, right: &&[&str]) -> bool {
+        left == *right
     }
 }
 
-impl PropertyValueCompare<'_, Vec<GString>> for &'_ [&'_ str] {
-    fn property_compare(left: Vec<GString>, right: &&[&str]) -> bool {
+impl PropertyValueCompare<'_, Vec<GString>> for Vec<&'_ str> {
+    fn property_compare(left: Vec<GString>, right: &Vec<&str>) -> bool {
         left == *right
     }
 }
 
-impl PropertyValueCompare<'_, Surface> for Option<Surface> {
-    fn property_compare(left: Surface, right: &Option<Surface>) -> bool {
-        if let Some(right) = right {
-            left.to_raw_none() == right.to_raw_none()
-        } else {
-            false
-        }
+impl PropertyValueCompare<'_, Vec<GString>> for Vec<String> {
+    fn property_compare(left: Vec<GString>, right: &Vec<String>) -> bool {
+        left.len() == right.len() && left.iter().zip(right).all(|(left, right)| left == right)
     }
 }
 
-impl PropertyValueCompare<'_, Option<Surface>> for Surface {
-    fn property_compare(left: Option<Surface>, right: &Surface) -> bool {
-        if let Some(left) = left {
-            left.to_raw_none() == right.to_raw_none()
-        } else {
-            false
+/// An [`Image`][Image] showing a named icon, which is equal to another showing the same
+/// icon at the same size.
+///
+/// [Image]: ../../gtk/struct.Image.html
+pub struct IconImage {
+    name: String,
+    size: IconSize,
+    image: Image,
+}
+
+impl PartialEq for IconImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.size == other.size
+    }
+}
+
+impl<'a> PropertyValueCompare<'a, Option<Widget>> for IconImage {
+    fn property_compare(left: Option<Widget>, right: &IconImage) -> bool {
+        Image::property_compare(left, &right.image)
+    }
+}
+
+impl<'a> PropertyValueCoerce<'a, Option<&'a Image>> for IconImage {
+    fn property_coerce(value: &'a IconImage) -> Option<&'a Image> {
+        Some(&value.image)
+    }
+}
+
+/// A Cairo [`Surface`][Surface], or nothing, which is equal to another only if they're the
+/// same surface.
+///
+/// [Surface]: ../../cairo/struct.Surface.html
+pub struct SurfaceValue(Option<Surface>);
+
+impl PartialEq for SurfaceValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(left), Some(right)) => left.to_raw_none() == right.to_raw_none(),
+            (None, None) => true,
+            _ => false,
         }
     }
 }
 
+impl PropertyValueCompare<'_, Surface> for SurfaceValue {
+    fn property_compare(left: Surface, right: &SurfaceValue) -> bool {
+        *right == SurfaceValue(Some(left))
+    }
+}
+
+impl PropertyValueCompare<'_, Option<Surface>> for SurfaceValue {
+    fn property_compare(left: Option<Surface>, right: &SurfaceValue) -> bool {
+        *right == SurfaceValue(left)
+    }
+}
+
+impl<'a> PropertyValueCoerce<'a, Option<&'a Surface>> for SurfaceValue {
+    fn property_coerce(value: &'a SurfaceValue) -> Option<&'a Surface> {
+        value.0.as_ref()
+    }
+}
+
 pub trait IntoPropertyValue<'a, A, Get, Set>
 where
     A: PropertyValueCompare<'a, Get> + PropertyValueCoerce<'a, Set> + 'a,
@@ -252,21 +362,449 @@ where
     }
 }
 
-impl<'a, Get, Set> IntoPropertyValue<'a, Image, Get, Set> for (&'_ str, IconSize)
+impl<'a, A, Get, Set> IntoPropertyValue<'a, A, Get, Set> for PropertyValue<'a, A, Get, Set>
 where
-    Image: PropertyValueCompare<'a, Get> + PropertyValueCoerce<'a, Set>,
+    A: PropertyValueCompare<'a, Get> + PropertyValueCoerce<'a, Set> + 'a,
 {
-    fn into_property_value(self) -> PropertyValue<'a, Image, Get, Set> {
+    fn into_property_value(self) -> PropertyValue<'a, A, Get, Set> {
+        self
+    }
+}
+
+impl<'a, Get, Set> IntoPropertyValue<'a, IconImage, Get, Set> for (&'_ str, IconSize)
+where
+    IconImage: PropertyValueCompare<'a, Get> + PropertyValueCoerce<'a, Set>,
+{
+    fn into_property_value(self) -> PropertyValue<'a, IconImage, Get, Set> {
         let (name, size) = self;
-        PropertyValue::new(Image::from_icon_name(Some(name), size))
+        PropertyValue::new(IconImage {
+            name: name.to_string(),
+            size,
+            image: Image::from_icon_name(Some(name), size),
+        })
+    }
+}
+
+impl<'a, Get, Set> IntoPropertyValue<'a, IconImage, Get, Set> for &'_ str
+where
+    IconImage: PropertyValueCompare<'a, Get> + PropertyValueCoerce<'a, Set>,
+{
+    fn into_property_value(self) -> PropertyValue<'a, IconImage, Get, Set> {
+        (self, IconSize::Button).into_property_value()
+    }
+}
+
+impl<'a, Get, Set> IntoPropertyValue<'a, SurfaceValue, Get, Set> for Surface
+where
+    SurfaceValue: PropertyValueCompare<'a, Get> + PropertyValueCoerce<'a, Set>,
+{
+    fn into_property_value(self) -> PropertyValue<'a, SurfaceValue, Get, Set> {
+        PropertyValue::new(SurfaceValue(Some(self)))
+    }
+}
+
+impl<'a, Get, Set> IntoPropertyValue<'a, SurfaceValue, Get, Set> for &'_ Surface
+where
+    SurfaceValue: PropertyValueCompare<'a, Get> + PropertyValueCoerce<'a, Set>,
+{
+    fn into_property_value(self) -> PropertyValue<'a, SurfaceValue, Get, Set> {
+        PropertyValue::new(SurfaceValue(Some(self.clone())))
+    }
+}
+
+impl<'a, Get, Set> IntoPropertyValue<'a, SurfaceValue, Get, Set> for Option<Surface>
+where
+    SurfaceValue: PropertyValueCompare<'a, Get> + PropertyValueCoerce<'a, Set>,
+{
+    fn into_property_value(self) -> PropertyValue<'a, SurfaceValue, Get, Set> {
+        PropertyValue::new(SurfaceValue(self))
     }
 }
 
-impl<'a, Get, Set> IntoPropertyValue<'a, Image, Get, Set> for &'_ str
+impl<'a, Get, Set> IntoPropertyValue<'a, SurfaceValue, Get, Set> for Option<&'_ Surface>
 where
-    Image: PropertyValueCompare<'a, Get> + PropertyValueCoerce<'a, Set>,
+    SurfaceValue: PropertyValueCompare<'a, Get> + PropertyValueCoerce<'a, Set>,
+{
+    fn into_property_value(self) -> PropertyValue<'a, SurfaceValue, Get, Set> {
+        PropertyValue::new(SurfaceValue(self.cloned()))
+    }
+}
+
+/// Mark a property value as live.
+///
+/// Between renders, `vgtk` remembers the value it last set each property to, and only sets
+/// it again when the view asks for a different value. That's not enough for a property the
+/// user can change, like the text of an [`Entry`][Entry] or the state of a
+/// [`ToggleButton`][ToggleButton]: if the view asks for the same value again after the user
+/// has changed it, it should be put back. A live value is compared with the object's current
+/// value instead, at the cost of calling the property's getter on every render.
+///
+/// The common properties of this kind are live without asking: `active` on a
+/// [`ToggleButton`][ToggleButton], `CheckMenuItem`, `Switch` or `ComboBox`, along with a
+/// `ComboBox`'s `active_id`, `text` on an [`Entry`][Entry], `value` on a `SpinButton`, `Range`
+/// or `Adjustment`, `state` on a `Switch`, `expanded` on an `Expander`, `current_page` on a
+/// `Notebook`, `position` on a `Paned` and `visible_child_name` on a `Stack`. Use this
+/// function for any other property, or [`register_live()`][register_live] to make a property
+/// of your own type live everywhere.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use vgtk::{gtk, VNode};
+/// # use vgtk::lib::gtk::{Entry, EntryExt};
+/// use vgtk::properties::live;
+///
+/// # fn view(text: &str) -> VNode<()> {
+/// gtk! {
+///     <Entry text=live(text) />
+/// }
+/// # }
+/// ```
+///
+/// [Entry]: ../../gtk/struct.Entry.html
+/// [ToggleButton]: ../../gtk/struct.ToggleButton.html
+/// [register_live]: fn.register_live.html
+pub fn live<'a, A, Get, Set>(
+    value: impl IntoPropertyValue<'a, A, Get, Set>,
+) -> PropertyValue<'a, A, Get, Set>
+where
+    A: PropertyValueCompare<'a, Get> + PropertyValueCoerce<'a, Set> + 'a,
 {
-    fn into_property_value(self) -> PropertyValue<'a, Image, Get, Set> {
-        PropertyValue::new(Image::from_icon_name(Some(self), IconSize::Button))
+    let mut value = value.into_property_value();
+    value.live = true;
+    value
+}
+
+struct LiveProperties {
+    properties: Vec<(Type, &'static str)>,
+    // Whether a property is live for an object type, by the type's id.
+    cache: HashMap<(usize, &'static str), bool>,
+}
+
+thread_local! {
+    static LIVE: RefCell<LiveProperties> = RefCell::new(LiveProperties {
+        properties: default_live(),
+        cache: HashMap::new(),
+    });
+}
+
+fn default_live() -> Vec<(Type, &'static str)> {
+    vec![
+        (ToggleButton::static_type(), "active"),
+        (CheckMenuItem::static_type(), "active"),
+        (Switch::static_type(), "active"),
+        (Switch::static_type(), "state"),
+        (ComboBox::static_type(), "active"),
+        (ComboBox::static_type(), "active_id"),
+        (Entry::static_type(), "text"),
+        (SpinButton::static_type(), "value"),
+        (Range::static_type(), "value"),
+        (Adjustment::static_type(), "value"),
+        (Expander::static_type(), "expanded"),
+        (Notebook::static_type(), "current_page"),
+        (Paned::static_type(), "position"),
+        (Stack::static_type(), "visible_child_name"),
+    ]
+}
+
+/// Register a property the user can change, so that it's always compared with the object's
+/// current value when patching, as if every value given to it were wrapped in
+/// [`live()`][live]. This also applies to subtypes of `A`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use vgtk::properties::register_live;
+/// # use vgtk::lib::gtk::Scale;
+/// register_live::<Scale>("fill_level");
+/// ```
+///
+/// [live]: fn.live.html
+pub fn register_live<A: IsA<Object>>(name: &'static str) {
+    LIVE.with(|live| {
+        let mut live = live.borrow_mut();
+        if !live.properties.contains(&(A::static_type(), name)) {
+            live.properties.push((A::static_type(), name));
+        }
+        live.cache.clear();
+    });
+}
+
+/// Whether the property `name` on `object` is one the user can change, which should always be
+/// compared with the object's current value.
+pub(crate) fn is_live(object: &Object, name: &'static str) -> bool {
+    let object_type = object.get_type();
+    LIVE.with(|live| {
+        let mut live = live.borrow_mut();
+        if let Some(known) = live.cache.get(&(object_type.to_glib(), name)) {
+            return *known;
+        }
+        let found = live
+            .properties
+            .iter()
+            .any(|(live_type, key)| *key == name && object_type.is_a(live_type));
+        live.cache.insert((object_type.to_glib(), name), found);
+        found
+    })
+}
+
+type ResetFn = Rc<dyn Fn(&Object)>;
+
+thread_local! {
+    static RESETS: RefCell<Vec<(Type, &'static str, ResetFn)>> = RefCell::new(default_resets());
+}
+
+fn default_resets() -> Vec<(Type, &'static str, ResetFn)> {
+    let mut resets: Vec<(Type, &'static str, ResetFn)> = vec![(
+        Label::static_type(),
+        "markup",
+        Rc::new(|object| object.downcast_ref::<Label>().unwrap().set_markup("")),
+    )];
+    // A `ComboBoxText`'s items are faked in ext.rs, and go back to none.
+    resets.push((
+        ComboBoxText::static_type(),
+        "items",
+        Rc::new(|object| object.downcast_ref::<ComboBoxText>().unwrap().remove_all()),
+    ));
+    // So are a `ListStore`'s rows, which go back to none as well.
+    resets.push((
+        ListStore::static_type(),
+        "rows",
+        Rc::new(|object| clear_rows(object.downcast_ref::<ListStore>().unwrap())),
+    ));
+    // `vgtk` shows every widget it builds, except for popovers, so a widget
+    // is visible unless the view says otherwise, whatever GTK's default.
+    resets.push((
+        Widget::static_type(),
+        "visible",
+        Rc::new(|object| {
+            let widget = object.downcast_ref::<Widget>().unwrap();
+            if widget.is::<Popover>() {
+                widget.hide();
+            } else {
+                widget.show();
+            }
+        }),
+    ));
+    // Many widgets change these in their constructors, like a `Button`,
+    // which can take focus even though `can_focus` defaults to false.
+    for name in ["can_focus", "sensitive"].iter() {
+        let name = *name;
+        resets.push((
+            Widget::static_type(),
+            name,
+            Rc::new(move |object| reset_to_initial_value(object, name)),
+        ));
+    }
+    // A menu item's properties are attributes, which are unset by setting
+    // them to nothing.
+    let attributes = [
+        ("label", "label"),
+        ("action", "action"),
+        ("detailed_action", "action"),
+        ("target", "target"),
+        ("icon_name", "icon"),
+        ("accel", "accel"),
+        ("hidden_when", "hidden-when"),
+    ];
+    for (name, attribute) in attributes.iter() {
+        let attribute = *attribute;
+        resets.push((
+            MenuItem::static_type(),
+            name,
+            Rc::new(move |object| {
+                let item = object.downcast_ref::<MenuItem>().unwrap();
+                item.set_attribute_value(attribute, None);
+            }),
+        ));
+    }
+    resets.push((
+        MenuItem::static_type(),
+        "is_section",
+        Rc::new(|object| {
+            let item = object.downcast_ref::<MenuItem>().unwrap();
+            item.set_is_section(false);
+        }),
+    ));
+    resets
+}
+
+/// Register a function to reset a property when it's left out of a render.
+///
+/// When a property which was set in one render is missing from the next, `vgtk` resets it to the
+/// default value of the GObject property with the same name. Properties which have a setter
+/// method but no GObject property behind them, such as those provided by the helper traits in
+/// [`vgtk::ext`][ext], need a reset function registered here instead, or they'll keep their last
+/// value. A reset function registered for a type also applies to its subtypes.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use vgtk::properties::register_reset;
+/// # use vgtk::lib::gtk::{Label, LabelExt};
+/// // A helper trait which lets you write `<Label subtitle="..." />`.
+/// pub trait SubtitleExt: LabelExt {
+///     fn set_subtitle(&self, subtitle: &str) {
+///         self.set_markup(&format!("<small>{}</small>", subtitle));
+///     }
+/// }
+///
+/// impl<A: LabelExt> SubtitleExt for A {}
+///
+/// register_reset::<Label>("subtitle", |label| label.set_subtitle(""));
+/// ```
+///
+/// [ext]: ../ext/index.html
+pub fn register_reset<A: IsA<Object>>(name: &'static str, reset: impl Fn(&A) + 'static) {
+    let reset: ResetFn = Rc::new(move |object: &Object| {
+        reset(object.downcast_ref::<A>().unwrap_or_else(|| {
+            panic!(
+                "reset function for {}::{} called on a {}",
+                A::static_type(),
+                name,
+                object.get_type()
+            )
+        }))
+    });
+    RESETS.with(|resets| {
+        let mut resets = resets.borrow_mut();
+        resets.retain(|(object_type, key, _)| (*object_type, *key) != (A::static_type(), name));
+        resets.push((A::static_type(), name, reset));
+    });
+}
+
+thread_local! {
+    // The value a property has in a freshly constructed object, by the
+    // object's type id.
+    static INITIAL_VALUES: RefCell<HashMap<(usize, &'static str), Option<Value>>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Reset the property `name` on `object` to the value it has in a freshly constructed object of
+/// the same type, which can differ from the GObject property's default value.
+fn reset_to_initial_value(object: &Object, name: &'static str) {
+    let object_type = object.get_type();
+    let property = name.replace('_', "-");
+    let initial = INITIAL_VALUES.with(|values| {
+        values
+            .borrow_mut()
+            .entry((object_type.to_glib(), name))
+            .or_insert_with(|| match Object::new(object_type, &[]) {
+                Ok(fresh) => {
+                    let value = fresh.get_property(property.as_str()).ok();
+                    if let Some(widget) = fresh.downcast_ref::<Widget>() {
+                        // Don't leave a fresh window behind among the toplevels.
+                        #[allow(unsafe_code)]
+                        unsafe {
+                            widget.destroy();
+                        }
+                    }
+                    value
+                }
+                Err(_) => None,
+            })
+            .clone()
+    });
+    match initial {
+        Some(value) => {
+            if let Err(error) = object.set_property(property.as_str(), &value) {
+                debug!("unable to reset {}::{}: {}", object_type, name, error);
+            }
+        }
+        None => reset_to_default(object, name),
+    }
+}
+
+/// Reset the property `name` on `object` to its default, using a reset function registered with
+/// [`register_reset()`][register_reset] or else the GObject property's default value.
+///
+/// [register_reset]: fn.register_reset.html
+pub(crate) fn reset_property(object: &Object, name: &'static str) {
+    let mut object_type = Some(object.get_type());
+    while let Some(current) = object_type {
+        let reset = RESETS.with(|resets| {
+            resets
+                .borrow()
+                .iter()
+                .find(|(object_type, key, _)| *object_type == current && *key == name)
+                .map(|(_, _, reset)| reset.clone())
+        });
+        if let Some(reset) = reset {
+            reset(object);
+            return;
+        }
+        object_type = current.parent();
+    }
+    reset_to_default(object, name);
+}
+
+/// Reset the property `name` on `object` to the GObject property's default value.
+fn reset_to_default(object: &Object, name: &'static str) {
+    let property = name.replace('_', "-");
+    match object.find_property(property.as_str()) {
+        Some(spec)
+            if spec.get_flags().contains(ParamFlags::WRITABLE)
+                && !spec.get_flags().contains(ParamFlags::CONSTRUCT_ONLY) =>
+        {
+            if let Some(default) = spec.get_default_value() {
+                if let Err(error) = object.set_property(property.as_str(), &default) {
+                    debug!("unable to reset {}::{}: {}", object.get_type(), name, error);
+                }
+            }
+        }
+        _ => debug!(
+            "don't know how to reset {}::{}; register a reset function for it",
+            object.get_type(),
+            name
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ext::{ComboBoxTextExtHelpers, ListStoreExtHelpers};
+    use crate::types::TreeRow;
+    use glib::ToValue;
+    use gtk::TreeModelExt;
+
+    #[derive(Clone, PartialEq)]
+    struct Row(u32);
+
+    impl TreeRow for Row {
+        type Key = u32;
+
+        fn column_types() -> Vec<Type> {
+            vec![Type::U32]
+        }
+
+        fn key(&self) -> Self::Key {
+            self.0
+        }
+
+        fn values(&self) -> Vec<Value> {
+            vec![self.0.to_value()]
+        }
+    }
+
+    // This needs GTK and a display, so it only runs with `--ignored`.
+    #[test]
+    #[ignore]
+    fn faked_properties_reset() {
+        gtk::init().expect("unable to initialise GTK");
+
+        let combo = ComboBoxText::new();
+        combo.set_items(&[
+            ("a".to_string(), "A".to_string()),
+            ("b".to_string(), "B".to_string()),
+        ]);
+        reset_property(combo.upcast_ref(), "items");
+        assert!(combo.get_items().is_empty());
+
+        let store = ListStore::new(&Row::column_types());
+        store.set_rows(&[Row(1), Row(2)]);
+        reset_property(store.upcast_ref(), "rows");
+        assert_eq!(0, store.iter_n_children(None));
+        assert!(store.get_rows::<Row>().is_empty());
     }
 }
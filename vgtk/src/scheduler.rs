@@ -0,0 +1,232 @@
+use glib::{Continue, MainContext, ObjectExt, PRIORITY_HIGH_IDLE};
+use gtk::prelude::WidgetExtManual;
+use gtk::{Widget, WidgetExt, Window};
+
+use std::cell::{Cell, RefCell};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::rc::{Rc, Weak};
+
+use log::trace;
+
+/// A component which can be asked to process its pending messages and then
+/// render, if it needs to.
+pub(crate) trait Flush {
+    fn flush(&mut self);
+}
+
+struct Pending {
+    depth: usize,
+    order: usize,
+    component: Weak<RefCell<dyn Flush>>,
+}
+
+impl Pending {
+    // The heap pops its greatest item first, which should be the shallowest
+    // component, and among those the one which asked first.
+    fn key(&self) -> (Reverse<usize>, Reverse<usize>) {
+        (Reverse(self.depth), Reverse(self.order))
+    }
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+#[derive(Default)]
+struct Scheduler {
+    pending: BinaryHeap<Pending>,
+    order: usize,
+    scheduled: bool,
+    flushing: bool,
+}
+
+thread_local! {
+    static SCHEDULER: RefCell<Scheduler> = RefCell::new(Default::default());
+}
+
+/// Ask for a component at `depth` in the component tree to be flushed at the
+/// start of the next frame.
+pub(crate) fn schedule(component: Weak<RefCell<dyn Flush>>, depth: usize) {
+    if push(component, depth) && !flush_next_frame() {
+        // Without a window on screen there are no frames, so flush as soon
+        // as the main loop is idle instead.
+        flush_when_idle();
+    }
+}
+
+// Add a component to the queue, returning whether a flush needs to be started
+// for it.
+fn push(component: Weak<RefCell<dyn Flush>>, depth: usize) -> bool {
+    SCHEDULER.with(|scheduler| {
+        let mut scheduler = scheduler.borrow_mut();
+        let order = scheduler.order;
+        scheduler.order += 1;
+        scheduler.pending.push(Pending {
+            depth,
+            order,
+            component,
+        });
+        !std::mem::replace(&mut scheduler.scheduled, true)
+    })
+}
+
+// Flush from the frame clock of a window on screen, before it lays out and
+// draws its next frame. However many messages arrive in between, each
+// component renders at most once a frame. Returns `false` if no window is on
+// screen.
+fn flush_next_frame() -> bool {
+    let window = Window::list_toplevels()
+        .into_iter()
+        .find(Widget::get_mapped);
+    let window = match window {
+        Some(window) => window,
+        None => return false,
+    };
+    let fallback = Fallback(Cell::new(false));
+    let unmap = Rc::new(RefCell::new(None));
+    let tick_unmap = unmap.clone();
+    let tick = window.add_tick_callback(move |window, _| {
+        if let Some(id) = tick_unmap.borrow_mut().take() {
+            window.disconnect(id);
+        }
+        flush_pending();
+        fallback.0.set(true);
+        Continue(false)
+    });
+    // A window which is taken off the screen stops drawing frames, so give
+    // up on it, which drops the callback and falls back.
+    let tick = RefCell::new(Some(tick));
+    *unmap.borrow_mut() = Some(window.connect_unmap(move |_| {
+        if let Some(tick) = tick.borrow_mut().take() {
+            tick.remove();
+        }
+    }));
+    true
+}
+
+fn flush_when_idle() {
+    let source = glib::idle_source_new(Some("vgtk::flush"), PRIORITY_HIGH_IDLE, || {
+        flush_pending();
+        Continue(false)
+    });
+    source.attach(Some(&MainContext::ref_thread_default()));
+}
+
+// Held by a frame callback, to flush when the main loop is idle instead if
+// the callback is dropped without having run, because its window went away.
+struct Fallback(Cell<bool>);
+
+impl Drop for Fallback {
+    fn drop(&mut self) {
+        if !self.0.get() {
+            flush_when_idle();
+        }
+    }
+}
+
+/// Flush every scheduled component, parents before their children, so that a
+/// child which gets new properties from its parent renders in the same pass.
+pub(crate) fn flush_pending() {
+    let flushing =
+        SCHEDULER.with(|scheduler| std::mem::replace(&mut scheduler.borrow_mut().flushing, true));
+    if flushing {
+        return;
+    }
+    while let Some(next) = SCHEDULER.with(|scheduler| scheduler.borrow_mut().pending.pop()) {
+        if let Some(component) = next.component.upgrade() {
+            match component.try_borrow_mut() {
+                Ok(mut component) => component.flush(),
+                // It's busy, so it'll schedule itself again if it needs to.
+                Err(_) => trace!("skipping a component which is already running"),
+            }
+        }
+    }
+    SCHEDULER.with(|scheduler| {
+        let mut scheduler = scheduler.borrow_mut();
+        scheduler.flushing = false;
+        scheduler.scheduled = false;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A component which renders once when it's dirty, and makes its
+    // children dirty when it does, like a parent giving them new properties.
+    struct Component {
+        name: &'static str,
+        depth: usize,
+        dirty: bool,
+        children: Vec<Rc<RefCell<Component>>>,
+        rendered: Rc<RefCell<Vec<&'static str>>>,
+    }
+
+    impl Flush for Component {
+        fn flush(&mut self) {
+            if !std::mem::replace(&mut self.dirty, false) {
+                return;
+            }
+            self.rendered.borrow_mut().push(self.name);
+            for child in &self.children {
+                child.borrow_mut().dirty = true;
+                let depth = child.borrow().depth;
+                let handle: Weak<RefCell<dyn Flush>> = Rc::downgrade(child) as _;
+                schedule(handle, depth);
+            }
+        }
+    }
+
+    #[test]
+    fn flush_parents_first_once_each() {
+        let rendered = Rc::new(RefCell::new(Vec::new()));
+        let component = |name, depth, children| {
+            Rc::new(RefCell::new(Component {
+                name,
+                depth,
+                dirty: true,
+                children,
+                rendered: rendered.clone(),
+            }))
+        };
+        let grandchild = component("grandchild", 2, vec![]);
+        let child = component("child", 1, vec![grandchild.clone()]);
+        let sibling = component("sibling", 1, vec![]);
+        let root = component("root", 0, vec![child.clone(), sibling.clone()]);
+
+        // Children ask first, and the child asks twice.
+        for component in &[&grandchild, &sibling, &child, &root, &child] {
+            let depth = component.borrow().depth;
+            let handle: Weak<RefCell<dyn Flush>> = Rc::downgrade(component) as _;
+            push(handle, depth);
+        }
+        crate::flush();
+
+        assert_eq!(
+            vec!["root", "sibling", "child", "grandchild"],
+            *rendered.borrow()
+        );
+        SCHEDULER.with(|scheduler| {
+            let scheduler = scheduler.borrow();
+            assert!(scheduler.pending.is_empty());
+            assert!(!scheduler.scheduled);
+        });
+    }
+}
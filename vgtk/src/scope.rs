@@ -1,5 +1,5 @@
 use std::any::TypeId;
-use std::fmt::{Debug, Error, Formatter};
+use std::fmt::{Debug, Display, Error, Formatter};
 use std::hash::{Hash, Hasher};
 use std::sync::{
     atomic::{AtomicPtr, AtomicUsize, Ordering},
@@ -10,6 +10,7 @@ use colored::Colorize;
 use log::debug;
 
 use futures::channel::mpsc::{TrySendError, UnboundedSender};
+use glib::MainContext;
 
 use crate::component::{Component, ComponentTask};
 
@@ -18,6 +19,7 @@ use crate::component::{Component, ComponentTask};
 /// [Component]: trait.Component.html
 pub struct Scope<C: Component> {
     name: &'static str,
+    depth: usize,
     muted: Arc<AtomicUsize>,
     channel: UnboundedSender<C::Message>,
 }
@@ -26,6 +28,7 @@ impl<C: Component> Scope<C> {
     pub(crate) fn new(name: &'static str, channel: UnboundedSender<C::Message>) -> Self {
         Scope {
             name,
+            depth: 0,
             muted: Default::default(),
             channel,
         }
@@ -36,6 +39,7 @@ impl<C: Component> Clone for Scope<C> {
     fn clone(&self) -> Self {
         Scope {
             name: self.name,
+            depth: self.depth,
             muted: self.muted.clone(),
             channel: self.channel.clone(),
         }
@@ -74,11 +78,17 @@ impl<C: 'static + Component> Scope<C> {
     ) -> Scope<Child> {
         Scope {
             name,
+            depth: self.depth + 1,
             muted: self.muted.clone(),
             channel,
         }
     }
 
+    /// How many components there are above this one.
+    pub(crate) fn depth(&self) -> usize {
+        self.depth
+    }
+
     pub(crate) fn is_muted(&self) -> bool {
         self.muted.load(Ordering::SeqCst) > 0
     }
@@ -140,12 +150,155 @@ impl<C: 'static + Component> Scope<C> {
         self.channel.unbounded_send(message)
     }
 
+    /// Get a [`Sender`][Sender] for sending messages to the component this
+    /// `Scope` belongs to from any thread.
+    ///
+    /// [Sender]: struct.Sender.html
+    pub fn sender(&self) -> Sender<C::Message> {
+        Sender {
+            name: self.name,
+            muted: self.muted.clone(),
+            channel: self.channel.clone(),
+        }
+    }
+
     /// Get the name of the component this `Scope` belongs to.
     pub fn name(&self) -> &'static str {
         &self.name
     }
 }
 
+/// A handle for sending messages to a [`Component`][Component] from any thread.
+///
+/// Get one from [`Scope::sender()`][sender]. Unlike a [`Scope`][Scope], a
+/// `Sender` is [`Send`][Send] and [`Sync`][Sync] and carries nothing but what it
+/// takes to deliver a message, so it can be handed to network clients, file
+/// watchers and other code running on threads of its own.
+///
+/// A message sent through a `Sender` wakes the GTK main loop and is passed to
+/// the component's [`Component::update()`][update] there, like any other
+/// message, and any rendering it asks for happens before the next frame along
+/// with everything else which asked. A message sent from the main thread while
+/// the component tree is rendering is dropped, as the render caused it, just
+/// like a message from a signal handler would be.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use vgtk::{ext::ApplicationHelpers, gtk, Component, UpdateAction, VNode};
+/// # use vgtk::lib::{gio::ApplicationFlags, gtk::Application};
+/// # #[derive(Clone, Debug)]
+/// # enum Message { Received(String) }
+/// # #[derive(Default)] struct App;
+/// # impl Component for App {
+/// #     type Message = Message; type Properties = ();
+/// #     fn view(&self) -> VNode<Self> {
+/// #         gtk! { <Application::new_unwrap(None, ApplicationFlags::empty()) /> }
+/// #     }
+/// # }
+/// let (app, scope) = vgtk::start::<App>();
+/// let sender = scope.sender();
+/// std::thread::spawn(move || loop {
+///     let line = String::from("hello");
+///     if sender.send(Message::Received(line)).is_err() {
+///         // The component has gone, so there's no one left to tell.
+///         break;
+///     }
+///     std::thread::sleep(std::time::Duration::from_secs(1));
+/// });
+/// ```
+///
+/// [Component]: trait.Component.html
+/// [Scope]: struct.Scope.html
+/// [sender]: struct.Scope.html#method.sender
+/// [update]: trait.Component.html#method.update
+/// [Send]: https://doc.rust-lang.org/std/marker/trait.Send.html
+/// [Sync]: https://doc.rust-lang.org/std/marker/trait.Sync.html
+pub struct Sender<Message> {
+    name: &'static str,
+    muted: Arc<AtomicUsize>,
+    channel: UnboundedSender<Message>,
+}
+
+impl<Message: Debug> Sender<Message> {
+    /// Send a message to the component.
+    ///
+    /// If the component has gone, the message is handed back in a
+    /// [`SendError`][SendError].
+    ///
+    /// [SendError]: struct.SendError.html
+    pub fn send(&self, message: Message) -> Result<(), SendError<Message>> {
+        let muted = self.muted.load(Ordering::SeqCst) > 0 && MainContext::default().is_owner();
+        debug!(
+            "{} {}: {}",
+            format!("Sender::send{}", if muted { " [muted]" } else { "" }).green(),
+            self.name.magenta().bold(),
+            format!("{:?}", message).bright_white().bold()
+        );
+        if muted {
+            return Ok(());
+        }
+        self.channel
+            .unbounded_send(message)
+            .map_err(|error| SendError(error.into_inner()))
+    }
+
+    /// Test whether the component has gone, so that sending it a message would
+    /// fail.
+    pub fn is_closed(&self) -> bool {
+        self.channel.is_closed()
+    }
+
+    /// Get the name of the component this `Sender` sends to.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<Message> Clone for Sender<Message> {
+    fn clone(&self) -> Self {
+        Sender {
+            name: self.name,
+            muted: self.muted.clone(),
+            channel: self.channel.clone(),
+        }
+    }
+}
+
+impl<Message> Debug for Sender<Message> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "Sender[{}]", self.name)
+    }
+}
+
+/// The error returned by [`Sender::send()`][send] when the component has gone,
+/// holding the message which couldn't be sent.
+///
+/// [send]: struct.Sender.html#method.send
+#[derive(Clone, PartialEq, Eq)]
+pub struct SendError<Message>(Message);
+
+impl<Message> SendError<Message> {
+    /// Take back the message which couldn't be sent.
+    pub fn into_inner(self) -> Message {
+        self.0
+    }
+}
+
+impl<Message> Debug for SendError<Message> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "SendError(..)")
+    }
+}
+
+impl<Message> Display for SendError<Message> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "the component has gone, so the message couldn't be sent")
+    }
+}
+
+impl<Message> std::error::Error for SendError<Message> {}
+
 pub(crate) struct AnyScope {
     type_id: TypeId,
     ptr: AtomicPtr<()>,
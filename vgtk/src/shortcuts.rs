@@ -0,0 +1,599 @@
+use glib::{Cast, Continue, IsA, Object, ObjectExt};
+use gtk::prelude::*;
+use gtk::{
+    Application, ApplicationWindow, ShortcutsGroupBuilder, ShortcutsSectionBuilder,
+    ShortcutsShortcutBuilder, ShortcutsWindow, ShortcutsWindowBuilder, Window,
+};
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+use colored::Colorize;
+use log::warn;
+
+// Where an application keeps its registry.
+const REGISTRY_KEY: &str = "vgtk-shortcuts";
+// Marks a help overlay which was generated from the registry, and can be
+// replaced when the registry changes.
+const GENERATED_KEY: &str = "vgtk-generated-help-overlay";
+
+/// The reason a shortcut couldn't be bound.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShortcutError {
+    /// GTK couldn't parse the accelerator.
+    InvalidAccelerator(String),
+    /// The accelerator is already bound to another action.
+    Conflict {
+        /// The accelerator which was asked for.
+        accelerator: String,
+        /// The action it's bound to.
+        action: String,
+    },
+}
+
+impl Display for ShortcutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShortcutError::InvalidAccelerator(accelerator) => {
+                write!(f, "invalid accelerator {:?}", accelerator)
+            }
+            ShortcutError::Conflict {
+                accelerator,
+                action,
+            } => write!(f, "{} is already bound to {}", accelerator, action),
+        }
+    }
+}
+
+impl std::error::Error for ShortcutError {}
+
+#[derive(Default)]
+struct Registry {
+    // The accelerators declared for each action which currently exists, by
+    // its detailed name, as they were written.
+    declared: BTreeMap<String, Vec<String>>,
+    // Accelerators bound at runtime, which win over the declared ones.
+    rebound: HashMap<String, Vec<String>>,
+    // Accelerators bound at runtime to actions which have gone away, for
+    // when they come back.
+    parked: HashMap<String, Vec<String>>,
+    titles: HashMap<String, String>,
+    // The conflicts we last reported, so we only report new ones.
+    reported: Vec<(String, Vec<String>)>,
+    refresh_pending: bool,
+}
+
+impl Registry {
+    // Every action with accelerators bound to it, declared or rebound.
+    fn actions(&self) -> BTreeSet<&String> {
+        self.declared.keys().chain(self.rebound.keys()).collect()
+    }
+
+    fn accels(&self, action: &str) -> Vec<String> {
+        match self.rebound.get(action) {
+            Some(accels) => accels.clone(),
+            // Declared accelerators which can't be parsed were reported when
+            // they were declared, and are left out.
+            None => self
+                .declared
+                .get(action)
+                .into_iter()
+                .flatten()
+                .filter(|accel| normalise(accel).is_ok())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn title(&self, action: &str) -> String {
+        match self.titles.get(action) {
+            Some(title) => title.clone(),
+            None => action.splitn(2, '.').last().unwrap_or(action).to_string(),
+        }
+    }
+
+    // Every accelerator bound to more than one action, with those actions.
+    fn conflicts(&self) -> Vec<(String, Vec<String>)> {
+        let mut bound: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for action in self.actions() {
+            for accel in self.accels(action) {
+                if let Ok(accel) = normalise(&accel) {
+                    bound.entry(accel).or_default().push(action.clone());
+                }
+            }
+        }
+        bound
+            .into_iter()
+            .filter(|(_, actions)| actions.len() > 1)
+            .collect()
+    }
+
+    fn declare(&mut self, action: &str, accelerators: &[&str]) {
+        for accelerator in accelerators {
+            if let Err(error) = normalise(accelerator) {
+                warn!(
+                    "{} {}: {}",
+                    "Invalid shortcut:".bright_red(),
+                    action.bright_cyan().bold(),
+                    error
+                );
+            }
+        }
+        let accelerators = accelerators.iter().map(|accel| accel.to_string()).collect();
+        self.declared.insert(action.to_string(), accelerators);
+        if let Some(accelerators) = self.parked.remove(action) {
+            self.rebound.insert(action.to_string(), accelerators);
+        }
+    }
+
+    fn rebind(&mut self, action: &str, accelerators: &[&str]) -> Result<(), ShortcutError> {
+        let mut bound = HashMap::new();
+        for other in self.actions().into_iter().filter(|other| *other != action) {
+            for accel in self.accels(other) {
+                if let Ok(accel) = normalise(&accel) {
+                    bound.insert(accel, other);
+                }
+            }
+        }
+        for accelerator in accelerators {
+            if let Some(other) = bound.get(&normalise(accelerator)?) {
+                return Err(ShortcutError::Conflict {
+                    accelerator: accelerator.to_string(),
+                    action: other.to_string(),
+                });
+            }
+        }
+        let accelerators = accelerators.iter().map(|accel| accel.to_string()).collect();
+        self.parked.remove(action);
+        self.rebound.insert(action.to_string(), accelerators);
+        Ok(())
+    }
+
+    // Returns whether the action had been rebound.
+    fn reset(&mut self, action: &str) -> bool {
+        self.parked.remove(action);
+        self.rebound.remove(action).is_some()
+    }
+
+    // Returns whether the action had any accelerators bound to it.
+    fn forget(&mut self, action: &str) -> bool {
+        self.titles.remove(action);
+        let declared = self.declared.remove(action).is_some();
+        match self.rebound.remove(action) {
+            Some(accelerators) => {
+                self.parked.insert(action.to_string(), accelerators);
+                true
+            }
+            None => declared,
+        }
+    }
+}
+
+// Parse an accelerator and name it the way GTK does, so that different ways
+// of writing the same accelerator compare equal.
+fn normalise(accelerator: &str) -> Result<String, ShortcutError> {
+    let invalid = || ShortcutError::InvalidAccelerator(accelerator.to_string());
+    let (key, mods) = gtk::accelerator_parse(accelerator);
+    if key == 0 {
+        return Err(invalid());
+    }
+    gtk::accelerator_name(key, mods)
+        .map(|name| name.to_string())
+        .ok_or_else(invalid)
+}
+
+/// An application's keyboard shortcuts.
+///
+/// The `Application::accels` and `ApplicationWindow::accels` child properties
+/// of actions declare their accelerators here, and the
+/// `Application::shortcut_title` and `ApplicationWindow::shortcut_title`
+/// child properties describe what they do. Accelerators are checked with
+/// [`accelerator_parse`][accelerator_parse] as they're declared, and any
+/// which can't be parsed is logged as a warning and left out. Any
+/// accelerator bound to more than one action, whether declared or rebound,
+/// is logged as a warning once the application has been rendered.
+///
+/// Shortcuts can be changed at runtime with [`rebind()`][rebind], which
+/// takes precedence over what the actions declare, and every
+/// `ApplicationWindow` without a help overlay of its own gets a
+/// [`ShortcutsWindow`][ShortcutsWindow] listing them, which the `win.show-help-overlay`
+/// action opens.
+///
+/// ```rust,no_run
+/// # use vgtk::{gtk, Component, UpdateAction, VNode, Shortcuts};
+/// # use vgtk::ext::*;
+/// # use vgtk::lib::gio::{prelude::*, ApplicationFlags, SimpleAction};
+/// # use vgtk::lib::gtk::*;
+/// # #[derive(Clone, Debug)]
+/// # enum Message { Save, SaveKey(String) }
+/// # #[derive(Default)]
+/// # struct Editor;
+/// # impl Component for Editor {
+/// # type Message = Message;
+/// # type Properties = ();
+/// fn update(&mut self, message: Message) -> UpdateAction<Self> {
+///     match message {
+///         Message::SaveKey(accel) => {
+///             let app = vgtk::current_object().unwrap().downcast::<Application>().unwrap();
+///             if let Err(error) = Shortcuts::for_application(&app).rebind("win.save", &[&accel]) {
+///                 eprintln!("Can't bind {}: {}", accel, error);
+///             }
+///             UpdateAction::None
+///         }
+///         Message::Save => UpdateAction::None,
+///     }
+/// }
+///
+/// fn view(&self) -> VNode<Self> {
+///     gtk! {
+///         <Application::new_unwrap(Some("com.example.editor"), ApplicationFlags::empty())>
+///             <ApplicationWindow>
+///                 <SimpleAction::new("save", None) ApplicationWindow::accels=["<Ctrl>s"].as_ref()
+///                     ApplicationWindow::shortcut_title="Save the document"
+///                     on activate = |_, _| Message::Save />
+///                 <Label label="Press Ctrl+? for a list of shortcuts." />
+///             </ApplicationWindow>
+///         </Application>
+///     }
+/// }
+/// # }
+/// ```
+///
+/// [accelerator_parse]: ../gtk/fn.accelerator_parse.html
+/// [rebind]: #method.rebind
+/// [ShortcutsWindow]: ../gtk/struct.ShortcutsWindow.html
+#[derive(Clone)]
+pub struct Shortcuts {
+    application: Application,
+    registry: Rc<RefCell<Registry>>,
+}
+
+impl Shortcuts {
+    /// Get the shortcut registry of an application.
+    pub fn for_application(application: &Application) -> Self {
+        if let Some(shortcuts) = Self::find(application) {
+            return shortcuts;
+        }
+        let registry: Rc<RefCell<Registry>> = Default::default();
+        #[allow(unsafe_code)]
+        unsafe {
+            application.set_data(REGISTRY_KEY, registry.clone());
+        }
+        application.connect_window_added(|application, window| {
+            if let Some(window) = window.downcast_ref::<ApplicationWindow>() {
+                if let Some(shortcuts) = Shortcuts::find(application) {
+                    shortcuts.refresh_help_overlay(window);
+                }
+            }
+        });
+        Shortcuts {
+            application: application.clone(),
+            registry,
+        }
+    }
+
+    // Get the registry of an application, if it has one yet.
+    fn find(application: &Application) -> Option<Self> {
+        // Only for_application() stores anything under this key.
+        #[allow(unsafe_code)]
+        let registry = unsafe { application.get_data::<Rc<RefCell<Registry>>>(REGISTRY_KEY) };
+        registry.map(|registry| Shortcuts {
+            application: application.clone(),
+            registry: registry.clone(),
+        })
+    }
+
+    /// Get the accelerators bound to an action, given its detailed name, like
+    /// `"win.save"`.
+    pub fn accels(&self, action: &str) -> Vec<String> {
+        self.registry.borrow().accels(action)
+    }
+
+    /// Bind an action to a new list of accelerators, in place of those it
+    /// declares, until [`reset()`][reset] is called for it.
+    ///
+    /// Fails without changing anything if an accelerator can't be parsed,
+    /// or is already bound to a different action.
+    ///
+    /// [reset]: #method.reset
+    pub fn rebind(&self, action: &str, accelerators: &[&str]) -> Result<(), ShortcutError> {
+        self.registry.borrow_mut().rebind(action, accelerators)?;
+        self.changed(action);
+        Ok(())
+    }
+
+    /// Go back to the accelerators an action declares, after it's been
+    /// bound to others with [`rebind()`][rebind].
+    ///
+    /// [rebind]: #method.rebind
+    pub fn reset(&self, action: &str) {
+        if self.registry.borrow_mut().reset(action) {
+            self.changed(action);
+        }
+    }
+
+    /// Get every accelerator which is bound to more than one action, with
+    /// the actions it's bound to.
+    pub fn conflicts(&self) -> Vec<(String, Vec<String>)> {
+        self.registry.borrow().conflicts()
+    }
+
+    /// Build a [`ShortcutsWindow`][ShortcutsWindow] listing the
+    /// application's shortcuts.
+    ///
+    /// [ShortcutsWindow]: ../gtk/struct.ShortcutsWindow.html
+    pub fn shortcuts_window(&self) -> ShortcutsWindow {
+        let registry = self.registry.borrow();
+        let section = ShortcutsSectionBuilder::new()
+            .section_name("shortcuts")
+            .visible(true)
+            .build();
+        for (prefix, title) in &[("app.", "Application"), ("win.", "Window")] {
+            let group = ShortcutsGroupBuilder::new()
+                .title(title)
+                .visible(true)
+                .build();
+            let mut empty = true;
+            for action in registry.actions() {
+                let accels = registry.accels(action);
+                if !action.starts_with(prefix) || accels.is_empty() {
+                    continue;
+                }
+                let shortcut = ShortcutsShortcutBuilder::new()
+                    .title(&registry.title(action))
+                    .accelerator(&accels.join(" "))
+                    .visible(true)
+                    .build();
+                group.add(&shortcut);
+                empty = false;
+            }
+            if !empty {
+                section.add(&group);
+            }
+        }
+        let window = ShortcutsWindowBuilder::new().modal(true).build();
+        window.add(&section);
+        #[allow(unsafe_code)]
+        unsafe {
+            window.set_data(GENERATED_KEY, true);
+        }
+        window
+    }
+
+    /// Declare the accelerators of an action, which are bound to it unless it
+    /// has been rebound. An accelerator which can't be parsed is logged as a
+    /// warning and left out.
+    pub(crate) fn declare(&self, action: &str, accelerators: &[&str]) {
+        self.registry.borrow_mut().declare(action, accelerators);
+        self.changed(action);
+    }
+
+    /// Get the accelerators an action declares.
+    pub(crate) fn declared(&self, action: &str) -> Vec<String> {
+        let registry = self.registry.borrow();
+        registry.declared.get(action).cloned().unwrap_or_default()
+    }
+
+    /// Describe an action in the help overlay.
+    pub(crate) fn set_title(&self, action: &str, title: &str) {
+        self.registry
+            .borrow_mut()
+            .titles
+            .insert(action.to_string(), title.to_string());
+        self.schedule_refresh();
+    }
+
+    /// Get the description of an action in the help overlay.
+    pub(crate) fn title(&self, action: &str) -> String {
+        self.registry.borrow().title(action)
+    }
+
+    /// Unbind the accelerators of an action which has gone away. Anything it
+    /// was rebound to is kept for when it comes back.
+    pub(crate) fn forget(&self, action: &str) {
+        let forgotten = self.registry.borrow_mut().forget(action);
+        if forgotten {
+            self.application.set_accels_for_action(action, &[]);
+            self.schedule_refresh();
+        }
+    }
+
+    fn changed(&self, action: &str) {
+        let accels = self.accels(action);
+        let accels: Vec<&str> = accels.iter().map(String::as_str).collect();
+        self.application.set_accels_for_action(action, &accels);
+        self.schedule_refresh();
+    }
+
+    // Report conflicts and update the help overlays once the current render
+    // is done, rather than for every action it declares.
+    fn schedule_refresh(&self) {
+        if std::mem::replace(&mut self.registry.borrow_mut().refresh_pending, true) {
+            return;
+        }
+        let shortcuts = self.clone();
+        glib::idle_add_local(move || {
+            shortcuts.refresh();
+            Continue(false)
+        });
+    }
+
+    fn refresh(&self) {
+        let conflicts = self.conflicts();
+        {
+            let mut registry = self.registry.borrow_mut();
+            registry.refresh_pending = false;
+            for (accel, actions) in &conflicts {
+                if !registry
+                    .reported
+                    .contains(&(accel.clone(), actions.clone()))
+                {
+                    warn!(
+                        "{} {} is bound to {}",
+                        "Shortcut conflict:".bright_red(),
+                        accel.bright_cyan().bold(),
+                        actions.join(", ")
+                    );
+                }
+            }
+            registry.reported = conflicts;
+        }
+        for window in self.application.get_windows() {
+            if let Ok(window) = window.downcast::<ApplicationWindow>() {
+                self.refresh_help_overlay(&window);
+            }
+        }
+    }
+
+    // Give a window a freshly generated help overlay, unless it has one of
+    // its own.
+    fn refresh_help_overlay(&self, window: &ApplicationWindow) {
+        let previous = window.get_help_overlay();
+        if let Some(ref overlay) = previous {
+            #[allow(unsafe_code)]
+            let generated = unsafe { overlay.get_data::<bool>(GENERATED_KEY).is_some() };
+            if !generated {
+                return;
+            }
+        }
+        if self.registry.borrow().actions().is_empty() {
+            window.set_help_overlay(None::<&ShortcutsWindow>);
+        } else {
+            window.set_help_overlay(Some(&self.shortcuts_window()));
+        }
+        // GTK keeps toplevel windows alive until they're destroyed.
+        if let Some(overlay) = previous {
+            #[allow(unsafe_code)]
+            unsafe {
+                overlay.destroy();
+            }
+        }
+    }
+}
+
+/// Run `f` with the shortcut registry of a window's application, now if the
+/// window has one, or when it gets one.
+pub(crate) fn with_window_shortcuts<W, F>(window: &W, f: F)
+where
+    W: IsA<Window>,
+    F: FnOnce(&Shortcuts) + 'static,
+{
+    if let Some(application) = window.get_application() {
+        f(&Shortcuts::for_application(&application));
+        return;
+    }
+    let f = RefCell::new(Some(f));
+    let id = Rc::new(RefCell::new(None));
+    let inner_id = id.clone();
+    *id.borrow_mut() = Some(window.connect_property_application_notify(move |window| {
+        if let Some(application) = window.get_application() {
+            if let Some(f) = f.borrow_mut().take() {
+                f(&Shortcuts::for_application(&application));
+            }
+            if let Some(id) = inner_id.borrow_mut().take() {
+                window.disconnect(id);
+            }
+        }
+    }));
+}
+
+/// Unbind the accelerators of an action which has been removed from an
+/// `Application` or `ApplicationWindow`.
+pub(crate) fn forget_action(parent: &Object, name: &str) {
+    if let Some(application) = parent.downcast_ref::<Application>() {
+        if let Some(shortcuts) = Shortcuts::find(application) {
+            shortcuts.forget(&format!("app.{}", name));
+        }
+    } else if let Some(window) = parent.downcast_ref::<ApplicationWindow>() {
+        if let Some(shortcuts) = window
+            .get_application()
+            .and_then(|application| Shortcuts::find(&application))
+        {
+            shortcuts.forget(&format!("win.{}", name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid(accelerator: &str) -> ShortcutError {
+        ShortcutError::InvalidAccelerator(accelerator.to_string())
+    }
+
+    // These need GTK and a display, so they only run with `--ignored`.
+    #[test]
+    #[ignore]
+    fn normalise_accelerators() {
+        gtk::init().expect("unable to initialise GTK");
+
+        assert_eq!(Ok("<Primary>s".to_string()), normalise("<Ctrl>s"));
+        assert_eq!(normalise("<Ctrl>s"), normalise("<Control>s"));
+        assert_eq!(normalise("<Shift><Alt>F2"), normalise("<Alt><Shift>F2"));
+        assert_eq!(Err(invalid("<Ctrl>")), normalise("<Ctrl>"));
+        assert_eq!(Err(invalid("not a key")), normalise("not a key"));
+    }
+
+    #[test]
+    #[ignore]
+    fn detect_conflicts() {
+        gtk::init().expect("unable to initialise GTK");
+
+        let mut registry = Registry::default();
+        registry.declare("app.quit", &["<Ctrl>q"]);
+        registry.declare("win.save", &["<Control>s", "not a key"]);
+        assert_eq!(vec!["<Control>s".to_string()], registry.accels("win.save"));
+        assert!(registry.conflicts().is_empty());
+
+        registry.declare("win.close", &["<ctrl>q"]);
+        let quit = (
+            "<Primary>q".to_string(),
+            vec!["app.quit".to_string(), "win.close".to_string()],
+        );
+        assert_eq!(vec![quit.clone()], registry.conflicts());
+
+        // An action which was never declared counts once it's rebound.
+        registry.rebind("win.open", &["<Ctrl>o"]).unwrap();
+        registry.declare("win.other", &["<Ctrl>o"]);
+        let open = (
+            "<Primary>o".to_string(),
+            vec!["win.open".to_string(), "win.other".to_string()],
+        );
+        assert_eq!(vec![open, quit], registry.conflicts());
+    }
+
+    #[test]
+    #[ignore]
+    fn rebind_actions() {
+        gtk::init().expect("unable to initialise GTK");
+
+        let mut registry = Registry::default();
+        registry.declare("win.save", &["<Ctrl>s"]);
+        registry.declare("win.open", &["<Ctrl>o"]);
+        assert_eq!(
+            Err(ShortcutError::Conflict {
+                accelerator: "<Control>o".to_string(),
+                action: "win.open".to_string(),
+            }),
+            registry.rebind("win.save", &["<Control>o"])
+        );
+        assert_eq!(Err(invalid("nope")), registry.rebind("win.save", &["nope"]));
+        assert_eq!(vec!["<Ctrl>s".to_string()], registry.accels("win.save"));
+
+        registry.rebind("win.save", &["F2"]).unwrap();
+        assert_eq!(vec!["F2".to_string()], registry.accels("win.save"));
+        assert!(registry.rebind("win.rename", &["F2"]).is_err());
+
+        // A rebound action gets its accelerators back when it returns.
+        assert!(registry.forget("win.save"));
+        assert!(registry.accels("win.save").is_empty());
+        registry.declare("win.save", &["<Ctrl>s"]);
+        assert_eq!(vec!["F2".to_string()], registry.accels("win.save"));
+
+        assert!(registry.reset("win.save"));
+        assert_eq!(vec!["<Ctrl>s".to_string()], registry.accels("win.save"));
+    }
+}
@@ -0,0 +1,235 @@
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver},
+    future::{abortable, AbortHandle, FutureExt},
+    stream::{self, Stream},
+    task::{Context, Poll},
+    StreamExt,
+};
+use gio::prelude::*;
+use gio::{Cancellable, File, FileMonitor, FileMonitorEvent, FileMonitorFlags};
+use glib::MainContext;
+
+use std::any::TypeId;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::{Debug, Error, Formatter};
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::time::Duration;
+
+use log::warn;
+
+use crate::component::Component;
+use crate::scope::Scope;
+
+type StartFn<Message> = Box<dyn FnOnce() -> Pin<Box<dyn Stream<Item = Message>>>>;
+
+/// A stream of messages a [`Component`][Component] wants to receive for as long
+/// as it asks for it.
+///
+/// A component lists its subscriptions in
+/// [`Component::subscriptions()`][subscriptions], which is called again after
+/// every update. Subscriptions are told apart by their ids: a subscription whose
+/// id wasn't in the previous list is started, one whose id has gone from the list
+/// is stopped, and one whose id is still there keeps running undisturbed, so
+/// the stream it's built from is only constructed when it starts. Every
+/// subscription is stopped when the component unmounts.
+///
+/// Every message the stream yields is passed to the component's
+/// [`Component::update()`][update]. A subscription whose stream runs out simply
+/// stops; it isn't started again unless its id leaves the list and comes back.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// # use vgtk::{gtk, Component, Subscription, UpdateAction, VNode};
+/// # use vgtk::lib::gtk::{Label, LabelExt};
+/// #[derive(Clone, Debug)]
+/// enum Message {
+///     Tick,
+///     Pause,
+/// }
+///
+/// #[derive(Default)]
+/// struct Clock {
+///     seconds: usize,
+///     paused: bool,
+/// }
+///
+/// impl Component for Clock {
+///     type Message = Message;
+///     type Properties = ();
+///
+///     fn update(&mut self, message: Message) -> UpdateAction<Self> {
+///         match message {
+///             Message::Tick => self.seconds += 1,
+///             Message::Pause => self.paused = !self.paused,
+///         }
+///         UpdateAction::Render
+///     }
+///
+///     fn subscriptions(&self) -> Vec<Subscription<Message>> {
+///         if self.paused {
+///             vec![]
+///         } else {
+///             vec![Subscription::interval("clock", Duration::from_secs(1), Message::Tick)]
+///         }
+///     }
+///
+///     fn view(&self) -> VNode<Self> {
+///         gtk! { <Label label=self.seconds.to_string() /> }
+///     }
+/// }
+/// ```
+///
+/// [Component]: trait.Component.html
+/// [subscriptions]: trait.Component.html#method.subscriptions
+/// [update]: trait.Component.html#method.update
+pub struct Subscription<Message> {
+    id: u64,
+    start: StartFn<Message>,
+}
+
+impl<Message: 'static> Subscription<Message> {
+    /// Construct a subscription to the stream returned by `start`.
+    ///
+    /// The `id` can be any value which implements [`Hash`][Hash], and should
+    /// identify the stream well enough that two subscriptions with the same id
+    /// can be treated as the same subscription. `start` is only called if no
+    /// subscription with that id is already running.
+    ///
+    /// [Hash]: https://doc.rust-lang.org/std/hash/trait.Hash.html
+    pub fn new<I, S, F>(id: I, start: F) -> Self
+    where
+        I: Hash + 'static,
+        S: Stream<Item = Message> + 'static,
+        F: FnOnce() -> S + 'static,
+    {
+        Subscription {
+            id: hash_id(id),
+            start: Box::new(move || start().boxed_local()),
+        }
+    }
+
+    /// Construct a subscription which sends `message` every `period`, starting
+    /// one `period` after it's started.
+    pub fn interval<I: Hash + 'static>(id: I, period: Duration, message: Message) -> Self
+    where
+        Message: Clone,
+    {
+        let millis = period.as_millis().min(u32::MAX as u128) as u32;
+        Subscription::new(id, move || {
+            glib::interval_stream(millis).map(move |()| message.clone())
+        })
+    }
+
+    /// Construct a subscription which watches `file` for changes.
+    ///
+    /// Each change GIO reports is passed to `filter`, along with the file the
+    /// change concerns and, for renames and moves, the other file involved,
+    /// and the messages it returns are sent on. If the file can't be watched,
+    /// a warning is logged and the subscription sends nothing.
+    pub fn file_monitor<I, F>(id: I, file: &File, filter: F) -> Self
+    where
+        I: Hash + 'static,
+        F: Fn(&File, Option<&File>, FileMonitorEvent) -> Option<Message> + 'static,
+    {
+        let file = file.clone();
+        Subscription::new(id, move || {
+            match file.monitor(FileMonitorFlags::WATCH_MOVES, None::<&Cancellable>) {
+                Ok(monitor) => {
+                    let (sender, receiver) = unbounded();
+                    monitor.connect_changed(move |_, file, other, event| {
+                        if let Some(message) = filter(file, other, event) {
+                            let _ = sender.unbounded_send(message);
+                        }
+                    });
+                    MonitorStream { monitor, receiver }.boxed_local()
+                }
+                Err(error) => {
+                    warn!(
+                        "unable to watch {:?} for changes: {}",
+                        file.get_uri(),
+                        error
+                    );
+                    stream::empty().boxed_local()
+                }
+            }
+        })
+    }
+
+    /// Turn the messages of this subscription into messages of another type.
+    ///
+    /// This is useful for passing a subcomponent's subscriptions through a
+    /// parent. The id stays the same.
+    pub fn map<Other, F>(self, f: F) -> Subscription<Other>
+    where
+        F: Fn(Message) -> Other + 'static,
+    {
+        let start = self.start;
+        Subscription {
+            id: self.id,
+            start: Box::new(move || start().map(f).boxed_local()),
+        }
+    }
+
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Start the stream on the main loop, sending its messages to `scope`.
+    ///
+    /// The returned handle stops it.
+    pub(crate) fn start<C>(self, scope: &Scope<C>) -> AbortHandle
+    where
+        C: 'static + Component<Message = Message>,
+    {
+        let mut stream = (self.start)();
+        let scope = scope.clone();
+        let (job, handle) = abortable(async move {
+            while let Some(message) = stream.next().await {
+                if scope.try_send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        MainContext::ref_thread_default().spawn_local(job.map(|_| ()));
+        handle
+    }
+}
+
+/// Hash an id, along with its type, so ids of different types are unlikely to
+/// collide.
+pub(crate) fn hash_id<I: Hash + 'static>(id: I) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    TypeId::of::<I>().hash(&mut hasher);
+    id.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<Message> Debug for Subscription<Message> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "Subscription({:016x})", self.id)
+    }
+}
+
+/// The changes reported by a `FileMonitor`, which is cancelled when the stream
+/// is dropped.
+struct MonitorStream<Message> {
+    monitor: FileMonitor,
+    receiver: UnboundedReceiver<Message>,
+}
+
+impl<Message> Stream for MonitorStream<Message> {
+    type Item = Message;
+
+    fn poll_next(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(ctx)
+    }
+}
+
+impl<Message> Drop for MonitorStream<Message> {
+    fn drop(&mut self) {
+        self.monitor.cancel();
+    }
+}
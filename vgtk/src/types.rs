@@ -1,5 +1,9 @@
 //! Useful types for GTK extensions.
 
+use std::hash::Hash;
+
+use glib::{Type, Value};
+
 /// Specifies the position of a widget in a [`Grid`][Grid].
 ///
 /// The primary use of this struct is to fetch the current
@@ -73,3 +77,41 @@ impl Default for GridPosition {
         }
     }
 }
+
+/// A row of a [`ListStore`][ListStore], for binding a list of rows to the store
+/// through its `rows` property.
+///
+/// Each row has a key, which identifies it when the list changes, and a value
+/// for each of the store's columns. See
+/// [`ListStoreExtHelpers`][ListStoreExtHelpers] for an example.
+///
+/// [ListStore]: ../../gtk/struct.ListStore.html
+/// [ListStoreExtHelpers]: ../ext/trait.ListStoreExtHelpers.html
+pub trait TreeRow: Clone + PartialEq + 'static {
+    /// The type of a row's key.
+    type Key: Eq + Hash;
+
+    /// The types of the store's columns, to build it with.
+    fn column_types() -> Vec<Type>;
+
+    /// The key which tells this row apart from the others.
+    fn key(&self) -> Self::Key;
+
+    /// The row's value for each column, in order.
+    fn values(&self) -> Vec<Value>;
+}
+
+/// An item of a [`VirtualList`][VirtualList].
+///
+/// Each item has a key, which identifies it when the list changes, so that
+/// only the rows of items which were added, removed, moved or changed are
+/// rendered again.
+///
+/// [VirtualList]: ../struct.VirtualList.html
+pub trait ListItem: Clone + PartialEq + 'static {
+    /// The type of an item's key.
+    type Key: Eq + Hash;
+
+    /// The key which tells this item apart from the others.
+    fn key(&self) -> Self::Key;
+}
@@ -1,13 +1,18 @@
 use futures::channel::mpsc::UnboundedSender;
 use glib::prelude::*;
-use glib::{MainContext, Object};
+use glib::{Object, WeakRef};
 use gtk::{prelude::*, Widget};
 
 use std::any::TypeId;
+use std::cell::RefCell;
 use std::marker::PhantomData;
+use std::rc::{Rc, Weak};
 
 use crate::component::{Component, ComponentMessage, ComponentTask};
+use crate::scheduler::{self, Flush};
 use crate::scope::Scope;
+use crate::vdom::gtk_state::patch_properties;
+use crate::vdom::State;
 use crate::vnode::component::AnyProps;
 use crate::vnode::{VComponent, VProperty};
 
@@ -16,9 +21,134 @@ trait PropertiesReceiver {
     fn unmounting(&self);
 }
 
+/// Where a parent has placed a subcomponent's root objects among their
+/// siblings.
+#[derive(Clone, Default)]
+pub(crate) enum Anchor {
+    /// First in the parent.
+    #[default]
+    Start,
+    /// Directly after this object.
+    After(WeakRef<Object>),
+    /// Directly after another subcomponent's root objects, wherever they are.
+    AfterComponent(Weak<RefCell<Roots>>),
+}
+
+impl Anchor {
+    /// Find the object this anchor is currently directly after, if any.
+    pub(crate) fn resolve(&self) -> Option<Object> {
+        match self {
+            Anchor::Start => None,
+            Anchor::After(object) => object.upgrade(),
+            Anchor::AfterComponent(roots) => roots.upgrade().and_then(|roots| {
+                let roots = roots.borrow();
+                match roots
+                    .objects()
+                    .into_iter()
+                    .rev()
+                    .find(|object| object.is::<Widget>())
+                {
+                    Some(object) => Some(object),
+                    None => roots.anchor.resolve(),
+                }
+            }),
+        }
+    }
+}
+
+/// The root objects of a component, shared between the component's task, which
+/// keeps them up to date as the component renders, and the `ComponentState` in
+/// its parent.
+#[derive(Default)]
+pub(crate) struct Roots {
+    objects: Vec<Object>,
+    /// Where the parent placed the root objects. A component needs this to
+    /// place any root objects it adds or replaces itself.
+    anchor: Anchor,
+    /// The child properties the parent gave the component, which any new
+    /// root objects also need.
+    child_props: Vec<VProperty>,
+    /// The child properties given to the components this component is the
+    /// root node of, which its root objects need too.
+    inherited_child_props: Vec<VProperty>,
+    /// If the component's root node is another component, that component's
+    /// roots, which are this component's roots too.
+    inner: Option<Rc<RefCell<Roots>>>,
+}
+
+impl Roots {
+    pub(crate) fn objects(&self) -> Vec<Object> {
+        match &self.inner {
+            Some(inner) => inner.borrow().objects(),
+            None => self.objects.clone(),
+        }
+    }
+
+    pub(crate) fn anchor(&self) -> Anchor {
+        self.anchor.clone()
+    }
+
+    pub(crate) fn set_anchor(&mut self, anchor: &Anchor) {
+        if let Some(inner) = &self.inner {
+            inner.borrow_mut().set_anchor(anchor);
+        }
+        self.anchor = anchor.clone();
+    }
+
+    /// Every child property the component's root objects need, those of the
+    /// outermost component first.
+    pub(crate) fn child_props(&self) -> Vec<VProperty> {
+        self.inherited_child_props
+            .iter()
+            .chain(self.child_props.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// Replace the child properties the parent gave the component, returning
+    /// the previous ones.
+    pub(crate) fn set_child_props(&mut self, child_props: &[VProperty]) -> Vec<VProperty> {
+        let previous = std::mem::replace(&mut self.child_props, child_props.to_vec());
+        self.pass_child_props_down();
+        previous
+    }
+
+    fn set_inherited_child_props(&mut self, child_props: Vec<VProperty>) {
+        self.inherited_child_props = child_props;
+        self.pass_child_props_down();
+    }
+
+    fn pass_child_props_down(&self) {
+        if let Some(inner) = &self.inner {
+            inner
+                .borrow_mut()
+                .set_inherited_child_props(self.child_props());
+        }
+    }
+
+    /// Update the roots from a component's root state after it's rendered.
+    pub(crate) fn update<Model: 'static + Component>(&mut self, root: &State<Model>) {
+        fn inner_roots<Model: 'static + Component>(
+            root: &State<Model>,
+        ) -> Option<Rc<RefCell<Roots>>> {
+            match root {
+                State::Component(state) => Some(state.roots.clone()),
+                State::Memo(state) => inner_roots(&state.inner),
+                _ => None,
+            }
+        }
+        self.objects = root.objects();
+        self.inner = inner_roots(root);
+        let anchor = self.anchor();
+        self.set_anchor(&anchor);
+        self.pass_child_props_down();
+    }
+}
+
 pub struct ComponentState<Model: Component> {
     parent: PhantomData<Model>,
-    pub(crate) object: Object,
+    roots: Rc<RefCell<Roots>>,
+    pub(crate) key: Option<String>,
     model_type: TypeId,
     state: Box<dyn PropertiesReceiver>,
 }
@@ -30,11 +160,12 @@ impl<Model: 'static + Component> ComponentState<Model> {
         child_props: &[VProperty],
         scope: &Scope<Model>,
     ) -> Self {
-        let (sub_state, object) =
-            SubcomponentState::<Child>::new(props, parent, child_props, scope);
+        let (sub_state, roots) = SubcomponentState::<Child>::new(props, parent, child_props, scope);
+        roots.borrow_mut().set_child_props(child_props);
         ComponentState {
             parent: PhantomData,
-            object,
+            roots,
+            key: None,
             model_type: TypeId::of::<Child>(),
             state: Box::new(sub_state),
         }
@@ -48,8 +179,9 @@ impl<Model: 'static + Component> ComponentState<Model> {
     ) -> bool {
         if self.model_type == spec.model_type {
             // Components have same type; update props
-            for prop in &spec.child_props {
-                (prop.set)(self.object.upcast_ref(), parent, false);
+            let previous = self.roots.borrow_mut().set_child_props(&spec.child_props);
+            for object in self.objects() {
+                patch_properties(&object, parent, &spec.child_props, &previous);
             }
             self.state.update(&spec.props);
             true
@@ -62,17 +194,34 @@ impl<Model: 'static + Component> ComponentState<Model> {
 
     pub fn unmount(self) {
         self.state.unmounting();
-        if let Ok(widget) = self.object.downcast::<Widget>() {
-            #[allow(unsafe_code)]
-            unsafe {
-                widget.destroy();
+        for object in self.objects() {
+            if let Ok(widget) = object.downcast::<Widget>() {
+                #[allow(unsafe_code)]
+                unsafe {
+                    widget.destroy();
+                }
             }
         }
     }
+
+    pub(crate) fn objects(&self) -> Vec<Object> {
+        self.roots.borrow().objects()
+    }
+
+    /// An anchor for placing a sibling directly after this component.
+    pub(crate) fn anchor_after(&self) -> Anchor {
+        Anchor::AfterComponent(Rc::downgrade(&self.roots))
+    }
+
+    pub(crate) fn set_anchor(&self, anchor: &Anchor) {
+        self.roots.borrow_mut().set_anchor(anchor);
+    }
 }
 
 pub(crate) struct SubcomponentState<Model: Component> {
     channel: UnboundedSender<ComponentMessage<Model>>,
+    task: Weak<RefCell<dyn Flush>>,
+    depth: usize,
 }
 
 impl<Model: 'static + Component> SubcomponentState<Model> {
@@ -81,15 +230,25 @@ impl<Model: 'static + Component> SubcomponentState<Model> {
         parent: Option<&Object>,
         child_props: &[VProperty],
         parent_scope: &Scope<P>,
-    ) -> (Self, Object) {
+    ) -> (Self, Rc<RefCell<Roots>>) {
         let props: Model::Properties = props.unwrap();
         let (channel, task) = ComponentTask::new(props, parent, Some(parent_scope));
-        let object = task.object().unwrap();
-        for prop in child_props {
-            (prop.set)(object.upcast_ref(), parent, true);
+        let roots = task.roots();
+        for object in roots.borrow().objects() {
+            for prop in child_props {
+                (prop.set)(object.upcast_ref(), parent, true);
+            }
         }
-        MainContext::ref_thread_default().spawn_local(task);
-        (SubcomponentState { channel }, object)
+        let task = task.spawn();
+        let depth = parent_scope.depth() + 1;
+        (
+            SubcomponentState {
+                channel,
+                task,
+                depth,
+            },
+            roots,
+        )
     }
 }
 
@@ -98,7 +257,10 @@ impl<Model: 'static + Component> PropertiesReceiver for SubcomponentState<Model>
         let props = raw_props.unwrap();
         self.channel
             .unbounded_send(ComponentMessage::Props(props))
-            .expect("failed to send props message over system channel")
+            .expect("failed to send props message over system channel");
+        // Have the child render in the same flush as its parent, rather than
+        // waiting for the main loop to get around to it.
+        scheduler::schedule(self.task.clone(), self.depth);
     }
 
     fn unmounting(&self) {
@@ -107,3 +269,66 @@ impl<Model: 'static + Component> PropertiesReceiver for SubcomponentState<Model>
             .expect("failed to send unmount message over system channel")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn child_prop(name: &'static str) -> VProperty {
+        VProperty {
+            name,
+            value: Rc::new(()),
+            equal: |_, _| true,
+            live: false,
+            set: Rc::new(|_, _, _| {}),
+        }
+    }
+
+    fn names(props: Vec<VProperty>) -> Vec<&'static str> {
+        props.into_iter().map(|prop| prop.name).collect()
+    }
+
+    #[test]
+    fn inner_roots_inherit_outer_child_props() {
+        let inner = Rc::new(RefCell::new(Roots::default()));
+        inner
+            .borrow_mut()
+            .set_child_props(&[child_prop("Box::expand")]);
+        let mut outer = Roots {
+            inner: Some(inner.clone()),
+            ..Default::default()
+        };
+        outer.set_child_props(&[child_prop("Grid::left")]);
+        assert_eq!(
+            names(inner.borrow().child_props()),
+            vec!["Grid::left", "Box::expand"]
+        );
+
+        outer.set_child_props(&[child_prop("Grid::top")]);
+        assert_eq!(
+            names(inner.borrow().child_props()),
+            vec!["Grid::top", "Box::expand"]
+        );
+    }
+
+    #[test]
+    fn child_props_pass_through_every_level() {
+        let innermost = Rc::new(RefCell::new(Roots::default()));
+        let middle = Rc::new(RefCell::new(Roots {
+            inner: Some(innermost.clone()),
+            ..Default::default()
+        }));
+        middle
+            .borrow_mut()
+            .set_child_props(&[child_prop("Box::fill")]);
+        let mut outer = Roots {
+            inner: Some(middle),
+            ..Default::default()
+        };
+        outer.set_child_props(&[child_prop("Grid::left")]);
+        assert_eq!(
+            names(innermost.borrow().child_props()),
+            vec!["Grid::left", "Box::fill"]
+        );
+    }
+}
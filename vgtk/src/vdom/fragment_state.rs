@@ -0,0 +1,53 @@
+use glib::Object;
+
+use super::gtk_state::patch_children;
+use super::{Anchor, State};
+use crate::component::Component;
+use crate::scope::Scope;
+use crate::vnode::VNode;
+
+/// The state of a fragment at the root of a component.
+///
+/// Fragments anywhere else are flattened into their parent's children, so
+/// the children here are never fragments themselves.
+pub(crate) struct FragmentState<Model: Component> {
+    children: Vec<State<Model>>,
+}
+
+impl<Model: 'static + Component> FragmentState<Model> {
+    // The children are built in full, but it's up to the parent to add them.
+    pub(crate) fn build(
+        vnode: &VNode<Model>,
+        parent: Option<&Object>,
+        scope: &Scope<Model>,
+    ) -> Self {
+        let children = VNode::flatten(std::iter::once(vnode))
+            .into_iter()
+            .map(|child_spec| State::build(child_spec, parent, scope))
+            .collect();
+        FragmentState { children }
+    }
+
+    pub(crate) fn patch(
+        &mut self,
+        vnode: &VNode<Model>,
+        parent: Option<&Object>,
+        anchor: &Anchor,
+        scope: &Scope<Model>,
+    ) -> bool {
+        let parent = parent.expect("a fragment can only be patched inside a parent object");
+        let specs = VNode::flatten(std::iter::once(vnode));
+        patch_children(parent, anchor, &mut self.children, &specs, scope);
+        true
+    }
+
+    pub(crate) fn unmount(self) {
+        for child in self.children {
+            child.unmount();
+        }
+    }
+
+    pub(crate) fn objects(&self) -> Vec<Object> {
+        self.children.iter().flat_map(State::objects).collect()
+    }
+}
@@ -1,20 +1,34 @@
-use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use gio::{Action, ActionExt, ActionMapExt};
+use gio::{
+    Action, ActionExt, ActionMap, ActionMapExt, Menu as GioMenu, MenuItem as GioMenuItem, MenuModel,
+};
 use glib::{prelude::*, Object, SignalHandlerId};
 use gtk::{
-    self, prelude::*, Application, ApplicationWindow, Bin, Box as GtkBox, Builder, Container,
-    Dialog, Grid, GridExt, HeaderBar, Menu, MenuButton, MenuItem, Notebook, ShortcutsWindow,
-    Widget, Window,
+    self, prelude::*, Application, ApplicationWindow, Bin, Box as GtkBox, Builder, CellRenderer,
+    ComboBox, Container, Dialog, FlowBox, FlowBoxChild, Grid, GridExt, HeaderBar, ListBox,
+    ListBoxRow, Menu, MenuButton, MenuItem, Notebook, Overlay, Paned, Popover, ResponseType,
+    ShortcutsWindow, Stack, TreeModel, TreeView, TreeViewColumn, Widget, Window,
 };
 
-use super::State;
+use super::{Anchor, State};
 use crate::component::Component;
+use crate::ext::{
+    add_widget_action, link_menu_item, mark_menu_item_changed, remove_widget_action,
+    take_menu_item_changed, DEFAULT_ACTION_PREFIX,
+};
+use crate::properties::reset_property;
 use crate::scope::Scope;
+use crate::shortcuts::forget_action;
 use crate::vnode::{VHandler, VNode, VObject, VProperty};
 
 pub(crate) struct GtkState<Model: Component> {
     pub(crate) object: Object,
+    pub(crate) key: Option<String>,
+    // The properties and child properties applied by the last render.
+    properties: Vec<VProperty>,
+    child_props: Vec<VProperty>,
     handlers: HashMap<(&'static str, &'static str), SignalHandlerId>,
     children: Vec<State<Model>>,
 }
@@ -39,28 +53,61 @@ fn build_obj<A: IsA<Object>, Model: Component>(spec: &VObject<Model>) -> A {
         .unwrap_or_else(|_| panic!("build_obj: cannot cast {} to {}", class, A::static_type()))
 }
 
+// An action's name, parameter type and state type can't change once it's
+// made, so compare them with those of the action the spec makes now.
+fn same_action<Model: Component>(action: &Action, spec: &VObject<Model>) -> bool {
+    if spec.constructor.is_none() {
+        return true;
+    }
+    let fresh: Action = build_obj(spec);
+    action.get_name() == fresh.get_name()
+        && action.get_parameter_type() == fresh.get_parameter_type()
+        && action.get_state_type() == fresh.get_state_type()
+}
+
 // Gtk has many strange ways of adding children to a parent.
 fn add_child<Model: Component>(
     parent: &Object,
+    child_specs: &[&VNode<Model>],
     index: usize,
-    total: usize,
-    child_spec: &VNode<Model>,
     child: &Object,
 ) {
+    let child_spec = child_specs[index];
+    // Actions don't count towards how many children a parent can take.
+    let total = child_specs.iter().filter(|spec| !is_action(spec)).count();
     if let Some(application) = parent.downcast_ref::<Application>() {
         if let Some(window) = child.downcast_ref::<Window>() {
             application.add_window(window);
         } else if let Some(action) = child.downcast_ref::<Action>() {
             application.add_action(action);
+        } else if let Some(menu) = child.downcast_ref::<MenuModel>() {
+            application.set_menubar(Some(menu));
         } else {
             panic!(
-                "Application's children must be Windows or Actions, but {} was found.",
+                "Application's children must be Windows, Actions or MenuModels, but {} was found.",
                 child.get_type()
             );
         }
+    } else if let Some(action) = child.downcast_ref::<Action>() {
+        // Actions: an ApplicationWindow keeps them itself, under the `win`
+        // prefix, and any other Widget keeps them in an action group of its
+        // own, under the prefix in their `action_prefix` child property.
+        if let Some(window) = parent.downcast_ref::<ApplicationWindow>() {
+            window.add_action(action);
+        } else if let Some(widget) = parent.downcast_ref::<Widget>() {
+            let prefix = string_child_prop(child_spec, "action_prefix");
+            let prefix = prefix.as_deref().unwrap_or(DEFAULT_ACTION_PREFIX);
+            add_widget_action(widget, action, prefix);
+        } else {
+            panic!(
+                "An Action's parent must be an Application or a Widget, but {} was found.",
+                parent.get_type()
+            );
+        }
     } else if let Some(button) = parent.downcast_ref::<MenuButton>() {
         // MenuButton: can only have a single child, either a `Menu` set with
-        // `set_popup` or any other `Widget` set with `set_popover`.
+        // `set_popup`, a `MenuModel` set with `set_menu_model`, or any other
+        // `Widget` set with `set_popover`.
         if total > 1 {
             panic!(
                 "MenuButton can only have 1 child, but {} were found.",
@@ -69,6 +116,8 @@ fn add_child<Model: Component>(
         }
         if let Some(menu) = child.downcast_ref::<Menu>() {
             button.set_popup(Some(menu));
+        } else if let Some(menu) = child.downcast_ref::<MenuModel>() {
+            button.set_menu_model(Some(menu));
         } else if let Some(widget) = child.downcast_ref::<Widget>() {
             button.set_popover(Some(widget));
         } else {
@@ -90,11 +139,64 @@ fn add_child<Model: Component>(
                 child.get_type()
             );
         }
+    } else if let Some(menu) = parent.downcast_ref::<GioMenu>() {
+        // gio::Menu: takes gio::MenuItems, and other menus as sections
+        // without a label. It keeps copies of them rather than the objects
+        // themselves, so we keep track of which is where.
+        let position = with_menu_items(menu, |items| items.len());
+        insert_menu_item(menu, position, child);
+    } else if let Some(item) = parent.downcast_ref::<GioMenuItem>() {
+        // gio::MenuItem: single child, a menu, linked as its submenu or
+        // section.
+        if total > 1 {
+            panic!(
+                "gio::MenuItem can only have 1 child, but {} were found.",
+                total
+            );
+        }
+        if let Some(menu) = child.downcast_ref::<MenuModel>() {
+            link_menu_item(item, Some(menu));
+        } else {
+            panic!(
+                "gio::MenuItem can only take children of type MenuModel, but {} was found.",
+                child.get_type()
+            );
+        }
+    } else if let Some(menu) = child.downcast_ref::<MenuModel>() {
+        // MenuModel: a Popover or a Menu widget shows it, and any other
+        // parent can't take it.
+        if let Some(popover) = parent.downcast_ref::<Popover>() {
+            popover.bind_model(Some(menu), None);
+        } else if let Some(parent) = parent.downcast_ref::<Menu>() {
+            parent.bind_model(Some(menu), None, true);
+        } else {
+            panic!(
+                "A MenuModel's parent must be an Application, a MenuButton, a Popover or a Menu, but {} was found.",
+                parent.get_type()
+            );
+        }
+    } else if let Some(popover) = child.downcast_ref::<Popover>() {
+        // Popover: isn't added to its parent at all, but points at it with
+        // set_relative_to().
+        if let Some(widget) = parent.downcast_ref::<Widget>() {
+            popover.set_relative_to(Some(widget));
+        } else {
+            panic!(
+                "A Popover's parent must be a Widget, but {} was found.",
+                parent.get_type()
+            );
+        }
     } else if let Some(dialog) = parent.downcast_ref::<Dialog>() {
         // Dialog: children must be added to the Dialog's content area through
-        // get_content_area().
+        // get_content_area(), except those with the Dialog::response child
+        // property (which is faked in ext.rs), which go in the action area
+        // through add_action_widget().
         if let Some(widget) = child.downcast_ref::<Widget>() {
-            dialog.get_content_area().add(widget);
+            if let Some(response) = response_child_prop(child_spec) {
+                dialog.add_action_widget(widget, response);
+            } else {
+                dialog.get_content_area().add(widget);
+            }
         } else {
             panic!(
                 "Dialog's children must be Widgets, but {} was found.",
@@ -102,24 +204,13 @@ fn add_child<Model: Component>(
             );
         }
     } else if let Some(window) = parent.downcast_ref::<ApplicationWindow>() {
-        // ApplicationWindow: takes any number of Actions, optionally one
-        // ShortcutsWindow added with `set_help_overlay()`, and either 1 or 2
-        // Widgets. If 1, it's the main widget. If 2, the first is added with
-        // `set_titlebar()` and the second is the main widget.
-        if let Some(action) = child.downcast_ref::<Action>() {
-            window.add_action(action);
-        } else if let Some(help_overlay) = child.downcast_ref::<ShortcutsWindow>() {
+        // ApplicationWindow: takes optionally one ShortcutsWindow added with
+        // `set_help_overlay()`, and Widgets, which follow the same rules as
+        // for Window.
+        if let Some(help_overlay) = child.downcast_ref::<ShortcutsWindow>() {
             window.set_help_overlay(Some(help_overlay));
         } else if let Some(widget) = child.downcast_ref::<Widget>() {
-            match window.get_child() {
-                None => window.add(widget),
-                Some(ref titlebar) if window.get_titlebar().is_none() => {
-                    window.remove(titlebar);
-                    window.set_titlebar(Some(titlebar));
-                    window.add(widget);
-                }
-                _ => panic!("ApplicationWindow can have at most two Widget children."),
-            }
+            add_window_widget(window.upcast_ref(), child_specs, index, widget);
         } else {
             panic!(
                 "ApplicationWindow's children must be Actions or Widgets, but {} was found.",
@@ -127,23 +218,32 @@ fn add_child<Model: Component>(
             );
         }
     } else if let Some(window) = parent.downcast_ref::<Window>() {
-        // Window: takes only 1 or 2 Widgets. If 1 widget child, it's the
-        // window's main widget. If 2, the first is the title bar and the second
-        // is the main widget. More than 2 goes boom.
         if let Some(widget) = child.downcast_ref::<Widget>() {
-            if total == 2 && index == 0 {
-                window.set_titlebar(Some(widget));
+            add_window_widget(window, child_specs, index, widget);
+        } else {
+            panic!(
+                "Window's children must be Widgets, but {} was found.",
+                child.get_type()
+            );
+        }
+    } else if let Some(parent) = parent.downcast_ref::<Overlay>() {
+        // Overlay: the first widget is the main child, and the rest are
+        // added on top of it with add_overlay().
+        if let Some(widget) = child.downcast_ref::<Widget>() {
+            if widget_rank(child_specs, index) == 0 {
+                parent.add(widget);
             } else {
-                window.add(widget);
+                parent.add_overlay(widget);
             }
         } else {
             panic!(
-                "Window's children must be Widgets, but {} was found.",
+                "Overlay's children must be Widgets, but {} was found.",
                 child.get_type()
             );
         }
     } else if let Some(parent) = parent.downcast_ref::<Bin>() {
         // Bin: can only have a single child.
+        let total = widget_count(child_specs);
         if total > 1 {
             panic!("Bins can only have 1 child, but {} were found.", total);
         }
@@ -189,6 +289,29 @@ fn add_child<Model: Component>(
                 child.get_type()
             );
         }
+    } else if let Some(parent) = parent.downcast_ref::<Paned>() {
+        // Paned: takes two widgets, placed with pack1() and pack2(). Their
+        // Paned::resize and Paned::shrink child properties are needed when
+        // packing, and default to what add1() and add2() would use.
+        let total = widget_count(child_specs);
+        if total > 2 {
+            panic!("Paned can only have 2 children, but {} were found.", total);
+        }
+        if let Some(widget) = child.downcast_ref::<Widget>() {
+            let first = widget_rank(child_specs, index) == 0;
+            let resize = bool_child_prop(child_spec, "resize").unwrap_or(!first);
+            let shrink = bool_child_prop(child_spec, "shrink").unwrap_or(true);
+            if first {
+                parent.pack1(widget, resize, shrink);
+            } else {
+                parent.pack2(widget, resize, shrink);
+            }
+        } else {
+            panic!(
+                "Paned's children must be Widgets, but {} was found.",
+                child.get_type()
+            );
+        }
     } else if let Some(parent) = parent.downcast_ref::<Grid>() {
         if let Some(widget) = child.downcast_ref::<Widget>() {
             // by default we put widgets in the top left corner of the grid
@@ -221,6 +344,51 @@ fn add_child<Model: Component>(
                 child.get_type()
             );
         }
+    } else if let Some(parent) = parent.downcast_ref::<Stack>() {
+        // Stack: a page with a name or a title is added with add_named() or
+        // add_titled(). The Stack::name and Stack::title child properties are
+        // real, so they get set again below, but a page should have its name
+        // from the start.
+        if let Some(widget) = child.downcast_ref::<Widget>() {
+            let name = string_child_prop(child_spec, "name");
+            let title = string_child_prop(child_spec, "title");
+            match (name, title) {
+                (Some(name), Some(title)) => parent.add_titled(widget, &name, &title),
+                (Some(name), None) => parent.add_named(widget, &name),
+                _ => parent.add(widget),
+            }
+        } else {
+            panic!(
+                "Stack's children must be Widgets, but {} was found.",
+                child.get_type()
+            );
+        }
+    } else if let Some(view) = parent.downcast_ref::<TreeView>() {
+        // TreeView: takes a TreeModel, set with set_model(), and any number of
+        // TreeViewColumns, inserted at their place among the other columns.
+        if let Some(model) = child.downcast_ref::<TreeModel>() {
+            view.set_model(Some(model));
+        } else if let Some(column) = child.downcast_ref::<TreeViewColumn>() {
+            view.insert_column(column, column_rank(child_specs, index) as i32);
+        } else {
+            panic!(
+                "TreeView's children must be TreeModels or TreeViewColumns, but {} was found.",
+                child.get_type()
+            );
+        }
+    } else if let Some(column) = parent.downcast_ref::<TreeViewColumn>() {
+        // TreeViewColumn: takes CellRenderers, packed with pack_start(). Their
+        // TreeViewColumn::expand child property (which is faked in ext.rs) is
+        // needed when packing, and defaults to true.
+        if let Some(cell) = child.downcast_ref::<CellRenderer>() {
+            let expand = bool_child_prop(child_spec, "expand").unwrap_or(true);
+            column.pack_start(cell, expand);
+        } else {
+            panic!(
+                "TreeViewColumn's children must be CellRenderers, but {} was found.",
+                child.get_type()
+            );
+        }
     } else if let Some(container) = parent.downcast_ref::<Container>() {
         if let Some(widget) = child.downcast_ref::<Widget>() {
             container.add(widget);
@@ -239,16 +407,591 @@ fn add_child<Model: Component>(
     }
 }
 
-fn remove_child(parent: &Object, child: &Object) {
+// The value of a string child property, if the child has it.
+fn string_child_prop<Model: Component>(child_spec: &VNode<Model>, name: &str) -> Option<String> {
+    let value = child_spec.get_child_prop(name)?.value.as_ref();
+    if let Some(value) = value.downcast_ref::<String>() {
+        Some(value.clone())
+    } else {
+        value.downcast_ref::<Option<String>>()?.clone()
+    }
+}
+
+// The response of a Dialog's action widget, if the child is one.
+fn response_child_prop<Model: Component>(child_spec: &VNode<Model>) -> Option<ResponseType> {
+    let value = child_spec.get_child_prop("response")?.value.as_ref();
+    value.downcast_ref::<ResponseType>().copied()
+}
+
+// The value of a boolean child property, if the child has it.
+fn bool_child_prop<Model: Component>(child_spec: &VNode<Model>, name: &str) -> Option<bool> {
+    let value = child_spec.get_child_prop(name)?.value.as_ref();
+    value.downcast_ref::<bool>().copied()
+}
+
+// Whether a child spec is a Popover, which points at its parent rather than
+// being inside it.
+fn is_popover<Model: Component>(child_spec: &VNode<Model>) -> bool {
+    match child_spec {
+        VNode::Object(object) => object.object_type.is_a(&Popover::static_type()),
+        _ => false,
+    }
+}
+
+// Whether a child spec is an Action, which goes in an action group rather
+// than inside its parent.
+fn is_action<Model: Component>(child_spec: &VNode<Model>) -> bool {
+    match child_spec {
+        VNode::Object(object) => object.object_type.is_a(&Action::static_type()),
+        _ => false,
+    }
+}
+
+// Whether a child spec is a MenuModel, which its parent shows rather than
+// having it inside.
+fn is_menu_model<Model: Component>(child_spec: &VNode<Model>) -> bool {
+    match child_spec {
+        VNode::Object(object) => object.object_type.is_a(&MenuModel::static_type()),
+        _ => false,
+    }
+}
+
+// How many children actually go inside the parent.
+fn widget_count<Model: Component>(child_specs: &[&VNode<Model>]) -> usize {
+    child_specs
+        .iter()
+        .filter(|spec| !is_popover(spec) && !is_action(spec) && !is_menu_model(spec))
+        .count()
+}
+
+// The position of a child among the children which actually go inside the
+// parent.
+fn widget_rank<Model: Component>(child_specs: &[&VNode<Model>], index: usize) -> usize {
+    widget_count(&child_specs[..index])
+}
+
+// The position of a TreeView's column among its other columns.
+fn column_rank<Model: Component>(child_specs: &[&VNode<Model>], index: usize) -> usize {
+    child_specs[..index]
+        .iter()
+        .filter(|spec| match spec {
+            VNode::Object(object) => object.object_type.is_a(&TreeViewColumn::static_type()),
+            _ => false,
+        })
+        .count()
+}
+
+// Whether a property refers to the object's children or to what its other
+// properties have set up, like the items of a ComboBoxText, and so has to be
+// set after them.
+fn applies_last(object: &Object, property: &VProperty) -> bool {
+    match property.name {
+        "visible_child_name" => object.is::<Stack>(),
+        "default_response" => object.is::<Dialog>(),
+        "active" | "active_id" => object.is::<ComboBox>(),
+        _ => false,
+    }
+}
+
+// Window: takes a main widget and optionally a title bar, which is the child
+// with the `titlebar` child property (which is faked in ext.rs) or, if no
+// child has it, the first of two widgets. More than that goes boom.
+fn add_window_widget<Model: Component>(
+    window: &Window,
+    child_specs: &[&VNode<Model>],
+    index: usize,
+    widget: &Widget,
+) {
+    if is_titlebar(child_specs, index) {
+        set_titlebar(window, Some(widget));
+    } else if window.get_child().is_some() {
+        panic!("A Window can only have one main widget and one title bar widget.");
+    } else {
+        window.add(widget);
+    }
+}
+
+// Whether a Window's child goes in its title bar rather than being its main
+// widget.
+fn is_titlebar<Model: Component>(child_specs: &[&VNode<Model>], index: usize) -> bool {
+    let marked = |child_spec: &&VNode<Model>| child_spec.get_child_prop("titlebar").is_some();
+    if is_popover(child_specs[index]) {
+        return false;
+    }
+    if child_specs.iter().any(marked) {
+        return marked(&child_specs[index]);
+    }
+    // Actions and help overlays don't count.
+    let mut widgets = child_specs
+        .iter()
+        .enumerate()
+        .filter(|(_, child_spec)| match child_spec {
+            VNode::Object(object) => {
+                object.object_type.is_a(&Widget::static_type())
+                    && !object.object_type.is_a(&Window::static_type())
+                    && !object.object_type.is_a(&Popover::static_type())
+            }
+            _ => true,
+        });
+    match (widgets.next(), widgets.next(), widgets.next()) {
+        (Some((first, _)), Some(_), None) => first == index,
+        _ => false,
+    }
+}
+
+// Whether a child we kept is in a different slot from the one it should be in
+// now. A Window's title bar and main widget each have a slot of their own, as
+// do an Overlay's main widget and a Paned's two widgets, and a Dialog has an
+// action area as well as its content area.
+fn slot_changed<Model: Component>(
+    parent: &Object,
+    child_specs: &[&VNode<Model>],
+    index: usize,
+    widget: &Widget,
+) -> bool {
+    if widget.is::<Window>() || widget.is::<Popover>() {
+        false
+    } else if let Some(dialog) = parent.downcast_ref::<Dialog>() {
+        (dialog.get_response_for_widget(widget) != ResponseType::None)
+            != response_child_prop(child_specs[index]).is_some()
+    } else if let Some(window) = parent.downcast_ref::<Window>() {
+        (window.get_titlebar().as_ref() == Some(widget)) != is_titlebar(child_specs, index)
+    } else if let Some(overlay) = parent.downcast_ref::<Overlay>() {
+        (overlay.get_child().as_ref() == Some(widget)) != (widget_rank(child_specs, index) == 0)
+    } else if let Some(paned) = parent.downcast_ref::<Paned>() {
+        (paned.get_child1().as_ref() == Some(widget)) != (widget_rank(child_specs, index) == 0)
+    } else {
+        false
+    }
+}
+
+/// Where a component's root widget sits in its parent, noted before the root
+/// is taken out so that a rebuilt root can go back in the same place.
+///
+/// Some containers tell their children apart by rank rather than by child
+/// properties: the title bar of a Window is the first of two widgets, the main
+/// child of an Overlay and the first pane of a Paned are the first widget. The
+/// rank is given to `add_child()` among as many placeholder siblings as the
+/// parent has, so it picks the same slot. A Stack or an Overlay can't reorder
+/// their children, so their position is noted as well.
+pub(super) struct Slot {
+    rank: usize,
+    total: usize,
+    position: Option<i32>,
+}
+
+impl Slot {
+    /// The slot `widget` is in now.
+    pub(super) fn of(parent: &Object, widget: &Widget) -> Self {
+        let mut slot = Slot {
+            rank: 0,
+            total: 1,
+            position: None,
+        };
+        if let Some(window) = parent.downcast_ref::<Window>() {
+            match window.get_titlebar() {
+                Some(titlebar) if &titlebar == widget => slot.total = 2,
+                Some(_) => {
+                    slot.rank = 1;
+                    slot.total = 2;
+                }
+                None => {}
+            }
+        } else if let Some(overlay) = parent.downcast_ref::<Overlay>() {
+            if overlay.get_child().as_ref() != Some(widget) {
+                slot.rank = 1;
+                slot.total = 2;
+                slot.position = Some(overlay.get_child_index(widget));
+            }
+        } else if let Some(paned) = parent.downcast_ref::<Paned>() {
+            if paned.get_child1().as_ref() == Some(widget) {
+                slot.total = if paned.get_child2().is_some() { 2 } else { 1 };
+            } else {
+                slot.rank = 1;
+                slot.total = 2;
+            }
+        } else if let Some(stack) = parent.downcast_ref::<Stack>() {
+            slot.position = Some(stack.get_child_position(widget));
+        }
+        slot
+    }
+
+    /// The slot a widget goes in when there was no single widget before it:
+    /// the first free one, if the parent has slots.
+    fn free(parent: &Object) -> Self {
+        let next_free = |taken: bool| Slot {
+            rank: if taken { 1 } else { 0 },
+            total: if taken { 2 } else { 1 },
+            position: None,
+        };
+        if let Some(window) = parent.downcast_ref::<Window>() {
+            // With a main widget there already, a second widget is the
+            // title bar, which comes first.
+            let taken = window.get_titlebar().is_none() && window.get_child().is_some();
+            Slot {
+                rank: 0,
+                total: if taken { 2 } else { 1 },
+                position: None,
+            }
+        } else if let Some(overlay) = parent.downcast_ref::<Overlay>() {
+            next_free(overlay.get_child().is_some())
+        } else if let Some(paned) = parent.downcast_ref::<Paned>() {
+            next_free(paned.get_child1().is_some())
+        } else {
+            next_free(false)
+        }
+    }
+}
+
+// A stand-in for a child spec, which only says what type of object the child
+// is and which child properties it has.
+fn placeholder<Model: Component>(
+    object_type: glib::Type,
+    child_props: &[VProperty],
+) -> VNode<Model> {
+    VNode::Object(VObject {
+        object_type,
+        constructor: None,
+        properties: Vec::new(),
+        child_props: child_props.to_vec(),
+        handlers: Vec::new(),
+        children: Vec::new(),
+        key: None,
+    })
+}
+
+// Popovers pop up as soon as they're shown, so they're left alone until
+// something asks for them, either their visible property or their
+// MenuButton.
+fn show(widget: &Widget) {
+    if !widget.is::<Popover>() {
+        widget.show();
+    }
+}
+
+// GTK warns when the title bar of a realized window changes, and then
+// unrealizes the window to make the change anyway, so we do that first.
+fn set_titlebar(window: &Window, titlebar: Option<&Widget>) {
+    let mapped = window.get_mapped();
+    if window.get_realized() {
+        window.unrealize();
+    }
+    window.set_titlebar(titlebar);
+    if mapped {
+        window.map();
+    }
+}
+
+// Whether a child's position among its siblings is significant, and should
+// follow the order of the child specs. Only widgets inside a container have
+// a position. Children placed through a faked child property have a slot of
+// their own rather than a position, as do a Window's title bar and main
+// widget, and a Grid places its children through child properties, which get
+// patched anyway, and a TreeView places its columns itself. A Popover isn't
+// inside its parent at all, and neither is an Action or a MenuModel.
+fn has_position<Model: Component>(parent: &Object, child_spec: &VNode<Model>) -> bool {
+    const PLACED: [&str; 5] = [
+        "center_widget",
+        "custom_title",
+        "action_widget_start",
+        "action_widget_end",
+        "response",
+    ];
+    parent.is::<Container>()
+        && !parent.is::<Grid>()
+        && !parent.is::<TreeView>()
+        && (parent.is::<Dialog>() || !parent.is::<Window>())
+        && !is_popover(child_spec)
+        && !is_action(child_spec)
+        && !is_menu_model(child_spec)
+        && PLACED
+            .iter()
+            .all(|name| child_spec.get_child_prop(name).is_none())
+}
+
+// Whether we know how to move a child to a different position within this
+// parent without removing and re-adding it.
+fn can_reorder(parent: &Object) -> bool {
+    parent.is::<Dialog>()
+        || parent.is::<GtkBox>()
+        || parent.is::<HeaderBar>()
+        || parent.is::<Notebook>()
+        || parent.is::<ListBox>()
+        || parent.is::<FlowBox>()
+}
+
+// ListBox and FlowBox wrap any child which isn't already a row in a row of
+// their own, and it's the row we need to move or remove.
+fn wrapping_row<Row: IsA<Widget>>(child: &Widget) -> Widget {
+    if child.is::<Row>() {
+        return child.clone();
+    }
+    match child.get_parent() {
+        Some(row) if row.is::<Row>() => row,
+        _ => child.clone(),
+    }
+}
+
+// Move a child which is already in the parent to the position directly after
+// the sibling `after`, or to the start if `after` is `None`. Each container
+// has its own idea of how to do this, if any; check with `can_reorder()`
+// first.
+fn reorder_child(parent: &Object, child: &Object, after: Option<&Object>) {
+    fn as_widget(child: &Object) -> &Widget {
+        child.downcast_ref::<Widget>().unwrap_or_else(|| {
+            panic!(
+                "Only Widgets can be reordered, but {} was found.",
+                child.get_type()
+            )
+        })
+    }
+    // Positions are counted before the child is taken out of its current
+    // one, so moving it forward means aiming one short.
+    fn target(current: i32, after: Option<i32>) -> i32 {
+        match after {
+            None => 0,
+            Some(after) if current < after => after,
+            Some(after) => after + 1,
+        }
+    }
+
+    let widget = as_widget(child);
+    let after = after.map(as_widget);
+    if let Some(dialog) = parent.downcast_ref::<Dialog>() {
+        let content_area = dialog.get_content_area();
+        let position = target(
+            content_area.get_child_position(widget),
+            after.map(|after| content_area.get_child_position(after)),
+        );
+        content_area.reorder_child(widget, position);
+    } else if let Some(parent) = parent.downcast_ref::<GtkBox>() {
+        let position = target(
+            parent.get_child_position(widget),
+            after.map(|after| parent.get_child_position(after)),
+        );
+        parent.reorder_child(widget, position);
+    } else if let Some(parent) = parent.downcast_ref::<HeaderBar>() {
+        let position = target(
+            HeaderBarExt::get_child_position(parent, widget),
+            after.map(|after| HeaderBarExt::get_child_position(parent, after)),
+        );
+        HeaderBarExt::set_child_position(parent, widget, position);
+    } else if let Some(parent) = parent.downcast_ref::<Notebook>() {
+        let page = |widget| parent.page_num(widget).map_or(-1, |page| page as i32);
+        let position = target(page(widget), after.map(page));
+        parent.reorder_child(widget, Some(position as u32));
+    } else if let Some(parent) = parent.downcast_ref::<ListBox>() {
+        // ListBox: there's no way to move a row, but it can be taken out and
+        // inserted again at its new position without losing any state.
+        let row = wrapping_row::<ListBoxRow>(widget);
+        let index = |row: &Widget| {
+            row.downcast_ref::<ListBoxRow>()
+                .map_or(-1, |row| row.get_index())
+        };
+        let position = target(
+            index(&row),
+            after.map(|after| index(&wrapping_row::<ListBoxRow>(after))),
+        );
+        parent.remove(&row);
+        parent.insert(&row, position);
+    } else if let Some(parent) = parent.downcast_ref::<FlowBox>() {
+        // FlowBox: same as ListBox.
+        let row = wrapping_row::<FlowBoxChild>(widget);
+        let index = |row: &Widget| {
+            row.downcast_ref::<FlowBoxChild>()
+                .map_or(-1, |row| row.get_index())
+        };
+        let position = target(
+            index(&row),
+            after.map(|after| index(&wrapping_row::<FlowBoxChild>(after))),
+        );
+        parent.remove(&row);
+        parent.insert(&row, position);
+    } else {
+        panic!(
+            "Don't know how to reorder children in a {}",
+            parent.get_type()
+        );
+    }
+}
+
+// Find the longest subsequence of `items` which is in increasing order, and
+// return the set of its items.
+pub(crate) fn longest_increasing_subsequence(items: &[usize]) -> HashSet<usize> {
+    // `tails[n]` is the index into `items` of the smallest item which ends an
+    // increasing subsequence of length `n + 1`.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut predecessors = vec![None; items.len()];
+    for (index, item) in items.iter().enumerate() {
+        let length = tails.partition_point(|&tail| items[tail] < *item);
+        if length > 0 {
+            predecessors[index] = Some(tails[length - 1]);
+        }
+        if length == tails.len() {
+            tails.push(index);
+        } else {
+            tails[length] = index;
+        }
+    }
+    let mut result = HashSet::new();
+    let mut next = tails.last().copied();
+    while let Some(index) = next {
+        result.insert(items[index]);
+        next = predecessors[index];
+    }
+    result
+}
+
+// Decide which children need moving to put them in order, given where each
+// one was among the children kept from the last render, or `None` for a child
+// which is new. Only the longest run of kept children which are already in
+// order stays where it is. A new child always moves, even if it was added in
+// the right place by accident, because the container may hold other objects,
+// like a subcomponent's siblings, after the children being patched.
+fn placement_moves(previous: &[Option<usize>]) -> Vec<bool> {
+    let kept: Vec<usize> = previous.iter().flatten().copied().collect();
+    let stay = longest_increasing_subsequence(&kept);
+    previous
+        .iter()
+        .map(|position| match position {
+            Some(position) => !stay.contains(position),
+            None => true,
+        })
+        .collect()
+}
+
+pub(super) fn remove_child(parent: &Object, child: &Object) {
     // There are also special cases for removing children.
-    if let Some(application) = parent.downcast_ref::<Application>() {
+    if let Some(popover) = child.downcast_ref::<Popover>() {
+        // Popover: stop pointing at the parent, or stop being a MenuButton's
+        // popover.
+        if let Some(button) = parent.downcast_ref::<MenuButton>() {
+            button.set_popover(None::<&Widget>);
+        } else {
+            popover.set_relative_to(None::<&Widget>);
+        }
+    } else if let Some(action) = child.downcast_ref::<Action>() {
+        // Action: take it out of the widget's action group it went in, or
+        // out of the Application or ApplicationWindow.
+        let removed = match parent.downcast_ref::<Widget>() {
+            Some(widget) => remove_widget_action(widget, action),
+            None => false,
+        };
+        if !removed {
+            if let Some(map) = parent.downcast_ref::<ActionMap>() {
+                let name = action.get_name().expect("Action unexpectedly has no name");
+                map.remove_action(&name);
+                forget_action(parent, &name);
+            } else {
+                panic!(
+                    "Don't know how to remove an Action from a {}.",
+                    parent.get_type()
+                );
+            }
+        }
+    } else if let Some(menu) = parent.downcast_ref::<GioMenu>() {
+        // gio::Menu: remove the copy of the item.
+        remove_menu_item(menu, child);
+    } else if child.is::<MenuModel>() {
+        // MenuModel: unset it wherever it was set.
+        if let Some(item) = parent.downcast_ref::<GioMenuItem>() {
+            link_menu_item(item, None);
+        } else if let Some(application) = parent.downcast_ref::<Application>() {
+            application.set_menubar(None::<&MenuModel>);
+        } else if let Some(button) = parent.downcast_ref::<MenuButton>() {
+            button.set_menu_model(None::<&MenuModel>);
+        } else if let Some(popover) = parent.downcast_ref::<Popover>() {
+            popover.bind_model(None::<&MenuModel>, None);
+        } else if let Some(menu) = parent.downcast_ref::<Menu>() {
+            menu.bind_model(None::<&MenuModel>, None, true);
+        } else {
+            panic!(
+                "Don't know how to remove a MenuModel from a {}.",
+                parent.get_type()
+            );
+        }
+    } else if let Some(application) = parent.downcast_ref::<Application>() {
         if let Some(window) = child.downcast_ref::<Window>() {
             application.remove_window(window);
-        } else if let Some(action) = child.downcast_ref::<Action>() {
-            application.remove_action(&action.get_name().expect("Action unexpectedly has no name"));
         } else {
             panic!(
-                "Applications can only contain Windows, but was asked to remove a {}.",
+                "Applications can only contain Windows and Actions, but was asked to remove a {}.",
+                child.get_type()
+            );
+        }
+    } else if let Some(dialog) = parent.downcast_ref::<Dialog>() {
+        // Dialog: children live in the content area, or the action area if
+        // they have a response.
+        if let Some(child_widget) = child.downcast_ref::<Widget>() {
+            if dialog.get_response_for_widget(child_widget) == ResponseType::None {
+                dialog.get_content_area().remove(child_widget);
+            } else if let Some(area) = child_widget.get_parent() {
+                area.downcast::<Container>()
+                    .expect("Dialog's action area isn't a Container")
+                    .remove(child_widget);
+            }
+        } else {
+            panic!(
+                "Dialogs can only contain Widgets but was asked to remove a {}.",
+                child.get_type()
+            );
+        }
+    } else if let Some(window) = parent.downcast_ref::<Window>() {
+        // Window: the title bar isn't a regular child, and has to be unset
+        // rather than removed.
+        if let Some(child_widget) = child.downcast_ref::<Widget>() {
+            if window.get_titlebar().as_ref() == Some(child_widget) {
+                set_titlebar(window, None);
+            } else {
+                window.remove(child_widget);
+            }
+        } else {
+            panic!(
+                "Windows can only contain Widgets but was asked to remove a {}.",
+                child.get_type()
+            );
+        }
+    } else if let Some(view) = parent.downcast_ref::<TreeView>() {
+        // TreeView: unset the model, or remove the column.
+        if child.is::<TreeModel>() {
+            view.set_model(None::<&TreeModel>);
+        } else if let Some(column) = child.downcast_ref::<TreeViewColumn>() {
+            view.remove_column(column);
+        } else {
+            panic!(
+                "TreeViews can only contain TreeModels and TreeViewColumns, but was asked to remove a {}.",
+                child.get_type()
+            );
+        }
+    } else if let Some(column) = parent.downcast_ref::<TreeViewColumn>() {
+        // TreeViewColumn: its cells are kept by its CellArea.
+        if let Some(cell) = child.downcast_ref::<CellRenderer>() {
+            if let Some(area) = column.get_area() {
+                area.remove(cell);
+            }
+        } else {
+            panic!(
+                "TreeViewColumns can only contain CellRenderers, but was asked to remove a {}.",
+                child.get_type()
+            );
+        }
+    } else if let Some(list_box) = parent.downcast_ref::<ListBox>() {
+        // ListBox: remove the row the child was wrapped in.
+        if let Some(child_widget) = child.downcast_ref::<Widget>() {
+            list_box.remove(&wrapping_row::<ListBoxRow>(child_widget));
+        } else {
+            panic!(
+                "ListBoxes can only contain Widgets but was asked to remove a {}.",
+                child.get_type()
+            );
+        }
+    } else if let Some(flow_box) = parent.downcast_ref::<FlowBox>() {
+        // FlowBox: remove the child the widget was wrapped in.
+        if let Some(child_widget) = child.downcast_ref::<Widget>() {
+            flow_box.remove(&wrapping_row::<FlowBoxChild>(child_widget));
+        } else {
+            panic!(
+                "FlowBoxes can only contain Widgets but was asked to remove a {}.",
                 child.get_type()
             );
         }
@@ -271,6 +1014,352 @@ fn remove_child(parent: &Object, child: &Object) {
     }
 }
 
+// Match up new children with old ones: keyed children by their keys, unkeyed
+// children in the order they appear. Returns the index of the old child each
+// new child matches, if any.
+fn match_children(old: &[Option<&str>], new: &[Option<&str>]) -> Vec<Option<usize>> {
+    let mut keyed = HashMap::new();
+    let mut unkeyed = VecDeque::new();
+    for (index, key) in old.iter().enumerate() {
+        match key {
+            Some(key) => {
+                keyed.insert(*key, index);
+            }
+            None => unkeyed.push_back(index),
+        }
+    }
+    new.iter()
+        .map(|key| match key {
+            Some(key) => keyed.remove(key),
+            None => unkeyed.pop_front(),
+        })
+        .collect()
+}
+
+/// Patch a list of children inside `parent` to match a list of child specs.
+/// A fragment spec is treated as a single child, so the children of an object
+/// should be flattened with `VNode::flatten()` first.
+///
+/// Children with a position are placed after `anchor`.
+pub(super) fn patch_children<Model: 'static + Component>(
+    parent: &Object,
+    anchor: &Anchor,
+    children: &mut Vec<State<Model>>,
+    child_specs: &[&VNode<Model>],
+    scope: &Scope<Model>,
+) {
+    let total = child_specs.len();
+
+    let mut matches = match_children(
+        &children.iter().map(State::key).collect::<Vec<_>>(),
+        &child_specs
+            .iter()
+            .map(|spec| spec.get_key())
+            .collect::<Vec<_>>(),
+    );
+    let mut old: Vec<Option<State<Model>>> = children.drain(..).map(Some).collect();
+    let mut new: Vec<Option<State<Model>>> = matches
+        .iter()
+        .map(|index| index.and_then(|index| old[index].take()))
+        .collect();
+
+    // Anything left unmatched has gone away.
+    for child in old.into_iter().flatten() {
+        for object in child.objects() {
+            remove_child(parent, &object);
+        }
+        child.unmount();
+    }
+
+    // Patch the children we kept, and build anything which is new or
+    // couldn't be patched.
+    for (index, child_spec) in child_specs.iter().enumerate() {
+        if let Some(mut child) = new[index].take() {
+            if child.patch(child_spec, Some(parent), scope) {
+                new[index] = Some(child);
+                continue;
+            }
+            for object in child.objects() {
+                remove_child(parent, &object);
+            }
+            child.unmount();
+            matches[index] = None;
+        }
+        new[index] = Some(State::build(child_spec, Some(parent), scope));
+    }
+
+    // A child we kept which has changed slots needs to be taken out and added
+    // again.
+    for (index, child) in new.iter().enumerate() {
+        if matches[index].is_none() {
+            continue;
+        }
+        let widgets = child.as_ref().unwrap().widgets();
+        let moved = widgets
+            .iter()
+            .any(|widget| slot_changed(parent, child_specs, index, widget));
+        if moved {
+            for widget in widgets.iter().filter(|widget| !widget.is::<Window>()) {
+                remove_child(parent, widget.upcast_ref());
+            }
+            matches[index] = None;
+        }
+    }
+
+    // Only children with a position among their siblings need placing.
+    let positioned: Vec<bool> = child_specs
+        .iter()
+        .map(|child_spec| has_position(parent, child_spec))
+        .collect();
+
+    if can_reorder(parent) {
+        // Add the new children at the end, then move them and everything
+        // which is out of place into position.
+        for index in 0..total {
+            if matches[index].is_none() {
+                let child = new[index].as_ref().unwrap();
+                for object in child.objects() {
+                    add_child(parent, child_specs, index, &object);
+                }
+                for widget in child.widgets() {
+                    show(&widget);
+                }
+            }
+        }
+        let placed: Vec<usize> = (0..total).filter(|&index| positioned[index]).collect();
+        let moves = placement_moves(
+            &placed
+                .iter()
+                .map(|&index| matches[index])
+                .collect::<Vec<_>>(),
+        );
+        let mut previous: Option<Object> = anchor.resolve();
+        for (&index, &moving) in placed.iter().zip(moves.iter()) {
+            for widget in new[index].as_ref().unwrap().widgets() {
+                let widget: Object = widget.upcast();
+                if moving {
+                    reorder_child(parent, &widget, previous.as_ref());
+                }
+                previous = Some(widget);
+            }
+        }
+    } else {
+        // This container can only append, so everything after the children
+        // which are still in order gets appended again in the new order,
+        // moving existing objects rather than rebuilding them.
+        let mut order: Vec<usize> = (0..total)
+            .filter(|&index| positioned[index] && matches[index].is_some())
+            .collect();
+        order.sort_by_key(|&index| matches[index]);
+        let desired = (0..total).filter(|&index| positioned[index]);
+        let settled = order
+            .iter()
+            .zip(desired)
+            .take_while(|(left, right)| *left == right)
+            .count();
+        let mut seen = 0;
+        for index in 0..total {
+            let child = new[index].as_ref().unwrap();
+            if positioned[index] {
+                seen += 1;
+                if seen <= settled {
+                    continue;
+                }
+            }
+            if matches[index].is_some() {
+                if positioned[index] {
+                    for widget in child.widgets() {
+                        remove_child(parent, widget.upcast_ref());
+                        add_child(parent, child_specs, index, widget.upcast_ref());
+                    }
+                }
+            } else {
+                for object in child.objects() {
+                    add_child(parent, child_specs, index, &object);
+                }
+                for widget in child.widgets() {
+                    show(&widget);
+                }
+            }
+        }
+    }
+
+    *children = new.into_iter().map(Option::unwrap).collect();
+    if let Some(menu) = parent.downcast_ref::<GioMenu>() {
+        sync_menu(menu, children);
+    }
+    set_anchors(parent, anchor, children, child_specs);
+}
+
+/// Put the objects of a component's rebuilt root into `parent`, the first
+/// widget in the `slot` the old root was in, if it was a single widget.
+///
+/// They're added with the component's `child_props`, which the parent's
+/// render would have given them, and any with a position go after `anchor`.
+pub(super) fn place_root<Model: 'static + Component>(
+    parent: &Object,
+    anchor: &Anchor,
+    slot: Option<Slot>,
+    root: &State<Model>,
+    child_props: &[VProperty],
+) {
+    let mut slot = slot;
+    let mut previous = anchor.resolve();
+    for object in root.objects() {
+        let widget = object.downcast_ref::<Widget>();
+        let here = match widget {
+            Some(_) => slot.take(),
+            None => None,
+        }
+        .unwrap_or_else(|| Slot::free(parent));
+        let mut specs: Vec<VNode<Model>> = (0..here.total)
+            .map(|_| placeholder(Widget::static_type(), &[]))
+            .collect();
+        specs[here.rank] = placeholder(object.get_type(), child_props);
+        let specs: Vec<&VNode<Model>> = specs.iter().collect();
+        add_child(parent, &specs, here.rank, &object);
+        if let Some(widget) = widget {
+            show(widget);
+            if has_position(parent, specs[here.rank]) {
+                if can_reorder(parent) {
+                    reorder_child(parent, &object, previous.as_ref());
+                } else if let Some(position) = here.position {
+                    if let Some(stack) = parent.downcast_ref::<Stack>() {
+                        stack.set_child_position(widget, position);
+                    } else if let Some(overlay) = parent.downcast_ref::<Overlay>() {
+                        overlay.reorder_overlay(widget, position);
+                    }
+                }
+                previous = Some(object.clone());
+            }
+        }
+    }
+    root.set_anchor(anchor);
+}
+
+// Where a gio::Menu keeps the objects its items were copied from, in order.
+const MENU_ITEMS_KEY: &str = "vgtk-menu-items";
+
+fn with_menu_items<T>(menu: &GioMenu, f: impl FnOnce(&mut Vec<Object>) -> T) -> T {
+    // Only this function stores anything under this key.
+    #[allow(unsafe_code)]
+    unsafe {
+        if menu
+            .get_data::<RefCell<Vec<Object>>>(MENU_ITEMS_KEY)
+            .is_none()
+        {
+            menu.set_data(MENU_ITEMS_KEY, RefCell::new(Vec::<Object>::new()));
+        }
+        let items = menu
+            .get_data::<RefCell<Vec<Object>>>(MENU_ITEMS_KEY)
+            .unwrap();
+        f(&mut items.borrow_mut())
+    }
+}
+
+// Copy a child into a gio::Menu at `position`.
+fn insert_menu_item(menu: &GioMenu, position: usize, child: &Object) {
+    let item = if let Some(item) = child.downcast_ref::<GioMenuItem>() {
+        take_menu_item_changed(item);
+        item.clone()
+    } else if let Some(section) = child.downcast_ref::<MenuModel>() {
+        GioMenuItem::new_section(None, section)
+    } else {
+        panic!(
+            "gio::Menu's children must be MenuItems or MenuModels, but {} was found.",
+            child.get_type()
+        );
+    };
+    menu.insert_item(position as i32, &item);
+    with_menu_items(menu, |items| items.insert(position, child.clone()));
+}
+
+// Remove the copy of a child from a gio::Menu.
+fn remove_menu_item(menu: &GioMenu, child: &Object) {
+    let position = with_menu_items(menu, |items| {
+        let position = items.iter().position(|item| item == child)?;
+        items.remove(position);
+        Some(position)
+    });
+    if let Some(position) = position {
+        menu.remove(position as i32);
+    }
+}
+
+// Bring a gio::Menu in line with its children, moving the copies of those
+// which are out of place, and copying those which have changed again.
+fn sync_menu<Model: 'static + Component>(menu: &GioMenu, children: &[State<Model>]) {
+    let objects = children.iter().flat_map(State::objects);
+    for (position, child) in objects.enumerate() {
+        let current = with_menu_items(menu, |items| items.iter().position(|item| *item == child));
+        let changed = match child.downcast_ref::<GioMenuItem>() {
+            Some(item) => take_menu_item_changed(item),
+            None => false,
+        };
+        if current == Some(position) && !changed {
+            continue;
+        }
+        remove_menu_item(menu, &child);
+        insert_menu_item(menu, position, &child);
+    }
+}
+
+/// Apply a list of properties to an object which had `previous` applied to it
+/// in the last render, skipping any which haven't changed. Properties which
+/// depend on the others are applied last.
+pub(super) fn patch_properties(
+    object: &Object,
+    parent: Option<&Object>,
+    properties: &[VProperty],
+    previous: &[VProperty],
+) {
+    for last in &[false, true] {
+        let properties = properties
+            .iter()
+            .enumerate()
+            .filter(|(_, prop)| applies_last(object, prop) == *last);
+        for (index, prop) in properties {
+            // Properties usually come in the same order as last time.
+            let old = match previous.get(index) {
+                Some(old) if old.name == prop.name => Some(old),
+                _ => previous.iter().find(|old| old.name == prop.name),
+            };
+            prop.patch(object, parent, old);
+        }
+    }
+}
+
+// Whether a list of properties would change anything if it was applied to an
+// object which had `previous` applied to it in the last render.
+fn properties_changed(properties: &[VProperty], previous: &[VProperty]) -> bool {
+    properties.len() != previous.len()
+        || properties.iter().any(
+            |prop| match previous.iter().find(|old| old.name == prop.name) {
+                Some(old) => prop.live || !(prop.equal)(prop.value.as_ref(), old.value.as_ref()),
+                None => true,
+            },
+        )
+}
+
+// Tell each subcomponent which sibling it follows, so it can put any objects
+// it adds to the parent in the right place.
+fn set_anchors<Model: 'static + Component>(
+    parent: &Object,
+    anchor: &Anchor,
+    children: &[State<Model>],
+    child_specs: &[&VNode<Model>],
+) {
+    let mut previous = anchor.clone();
+    for (child, child_spec) in children.iter().zip(child_specs) {
+        if has_position(parent, child_spec) {
+            child.set_anchor(&previous);
+            if let Some(anchor) = child.anchor_after() {
+                previous = anchor;
+            }
+        }
+    }
+}
+
 impl<Model: 'static + Component> GtkState<Model> {
     // This function build the root object, but not its children. You must call
     // `build_children()` to finalise construction.
@@ -282,9 +1371,11 @@ impl<Model: 'static + Component> GtkState<Model> {
         // Build this object
         let object: Object = build_obj(&vobj);
 
-        // Apply properties
+        // Apply properties, except those which have to wait for children
         for prop in &vobj.properties {
-            (prop.set)(object.upcast_ref(), parent, true);
+            if !applies_last(&object, prop) {
+                (prop.set)(object.upcast_ref(), parent, true);
+            }
         }
 
         // Apply handlers
@@ -296,6 +1387,9 @@ impl<Model: 'static + Component> GtkState<Model> {
 
         GtkState {
             object: object.upcast(),
+            key: vobj.key.clone(),
+            properties: vobj.properties.clone(),
+            child_props: vobj.child_props.clone(),
             handlers,
             children: Vec::new(),
         }
@@ -304,17 +1398,29 @@ impl<Model: 'static + Component> GtkState<Model> {
     pub(crate) fn build_children(&mut self, vobj: &VObject<Model>, scope: &Scope<Model>) {
         let object = &self.object;
         // Build children
-        let total_children = vobj.children.len();
-        for (index, child_spec) in vobj.children.iter().enumerate() {
+        let child_specs = VNode::flatten(&vobj.children);
+        for (index, child_spec) in child_specs.iter().enumerate() {
             let child = State::build(child_spec, Some(&object), &scope);
-            let child_object = child.object().clone();
-            add_child(&object, index, total_children, child_spec, &child_object);
+            for child_object in child.objects() {
+                add_child(&object, &child_specs, index, &child_object);
+            }
             self.children.push(child);
         }
+        set_anchors(object, &Anchor::Start, &self.children, &child_specs);
+
+        // Apply the properties which were waiting for children. Handlers are
+        // connected by now, and shouldn't hear about it.
+        scope.mute();
+        for prop in &vobj.properties {
+            if applies_last(object, prop) {
+                (prop.set)(object, None, true);
+            }
+        }
+        scope.unmute();
 
         // Show this object, if it's a widget
         if let Some(widget) = self.object.downcast_ref::<Widget>() {
-            widget.show();
+            show(widget);
         }
     }
 
@@ -334,120 +1440,38 @@ impl<Model: 'static + Component> GtkState<Model> {
         parent: Option<&Object>,
         scope: &Scope<Model>,
     ) -> bool {
-        // Patch children
-        let mut to_remove = None;
-        let mut to_append = Vec::new();
-        let mut reconstruct_from = None;
-        for index in 0..(self.children.len().max(vobj.children.len())) {
-            match (self.children.get_mut(index), vobj.children.get(index)) {
-                (Some(State::Component(target)), Some(spec_item)) => {
-                    match spec_item {
-                        VNode::Object(_) => {
-                            // Component has become a widget; reconstruct from here
-                            reconstruct_from = Some(index);
-                            break;
-                        }
-                        VNode::Component(ref spec) => {
-                            if !target.patch(spec, Some(&self.object), scope) {
-                                reconstruct_from = Some(index);
-                                break;
-                            }
-                        }
-                    }
-                }
-                (Some(State::Gtk(target)), Some(spec_item)) => {
-                    match spec_item {
-                        VNode::Object(ref spec) => {
-                            if target.object.get_type() == spec.object_type {
-                                // Objects have same type; patch down
-                                target.patch(spec, Some(&self.object), scope);
-                            } else {
-                                // Objects are different, need to reconstruct everything from here
-                                reconstruct_from = Some(index);
-                                break;
-                            }
-                        }
-                        VNode::Component(_) => {
-                            // Gtk object has turned into a component; reconstruct from here
-                            reconstruct_from = Some(index);
-                            break;
-                        }
-                    }
-                }
-                (Some(_), None) => {
-                    // Extraneous Gtk object; delete
-                    if to_remove.is_none() {
-                        to_remove = Some(index);
-                    }
-                    break;
-                }
-                (None, Some(spec)) => {
-                    // New spec; construct
-                    let state = State::build(spec, Some(&self.object), scope);
-                    add_child(
-                        &self.object,
-                        index,
-                        vobj.children.len(),
-                        spec,
-                        state.object(),
-                    );
-                    to_append.push(state);
-                }
-                (None, None) => break,
-            }
-        }
-        if let Some(index) = reconstruct_from {
-            // Remove all previous children from here onwards
-            if self.object.is::<Window>() && index == 0 && self.children.len() == 2 {
-                panic!("Can't remove a title bar widget from an existing Window!");
-            }
-            for child in self.children.drain(index..) {
-                remove_child(&self.object, child.object());
-                child.unmount();
-            }
-            // Rebuild children from new specs
-            for (index, child_spec) in vobj.children.iter().enumerate().skip(index) {
-                let state = State::build(child_spec, Some(&self.object), scope);
-                add_child(
-                    &self.object,
-                    index,
-                    vobj.children.len(),
-                    child_spec,
-                    state.object(),
-                );
-                if let Some(w) = state.widget() {
-                    w.show()
-                }
-                self.children.push(state);
+        // An action which has changed its name or types has to be rebuilt.
+        if let Some(action) = self.object.downcast_ref::<Action>() {
+            if !same_action(action, vobj) {
+                return false;
             }
-        } else {
-            // Remove children flagged as extraneous
-            if let Some(remove_from) = to_remove {
-                if self.object.is::<Window>() && remove_from == 1 && self.children.len() == 2 {
-                    panic!("Can't remove a title bar widget from an existing Window!");
-                }
-                for child in self.children.drain(remove_from..) {
-                    remove_child(&self.object, &child.object());
-                    child.unmount();
-                }
-            }
-            // Or append newly constructed children
-            if self.object.is::<Window>() && !to_append.is_empty() && self.children.len() == 1 {
-                panic!("Can't add a title bar widget to an existing Window!");
-            }
-            for child in to_append {
-                if let Some(w) = child.widget() {
-                    w.show()
-                }
-                self.children.push(child);
+        }
+
+        // Patch children
+        let child_specs = VNode::flatten(&vobj.children);
+        patch_children(
+            &self.object,
+            &Anchor::Start,
+            &mut self.children,
+            &child_specs,
+            scope,
+        );
+
+        // A gio::Menu only has a copy of a menu item, which needs replacing
+        // when the item changes.
+        if let Some(item) = self.object.downcast_ref::<GioMenuItem>() {
+            if properties_changed(&vobj.properties, &self.properties) {
+                mark_menu_item_changed(item);
             }
         }
 
-        // Patch properties
-        self.patch_properties(&vobj.properties, parent);
+        // Patch properties, and reset any which have gone away
+        patch_properties(&self.object, parent, &vobj.properties, &self.properties);
+        self.reset_properties(&vobj.properties);
 
         // Patch child properties
-        self.patch_properties(&vobj.child_props, parent);
+        patch_properties(&self.object, parent, &vobj.child_props, &self.child_props);
+        self.child_props = vobj.child_props.clone();
 
         // Patch handlers
         self.patch_handlers(&vobj.handlers, scope);
@@ -455,33 +1479,33 @@ impl<Model: 'static + Component> GtkState<Model> {
         true
     }
 
-    fn patch_properties(&mut self, properties: &[VProperty], parent: Option<&Object>) {
-        for prop in properties {
-            (prop.set)(self.object.upcast_ref(), parent, false);
+    fn reset_properties(&mut self, properties: &[VProperty]) {
+        let names: HashSet<&'static str> = properties.iter().map(|prop| prop.name).collect();
+        for prop in &self.properties {
+            if !names.contains(prop.name) {
+                reset_property(&self.object, prop.name);
+            }
         }
+        self.properties = properties.to_vec();
     }
 
     fn patch_handlers(&mut self, handlers: &[VHandler<Model>], scope: &Scope<Model>) {
-        // FIXME need to store and match IDs
-        let mut seen = HashSet::new();
-        let mut remove = Vec::new();
+        // A handler may have captured different values since it was last
+        // connected, and there's no way to tell, so we always reconnect it to
+        // make sure the signal calls the handler from the latest render.
+        let object: &Object = self.object.upcast_ref();
+        let mut connected = HashMap::with_capacity(handlers.len());
         for handler in handlers {
             let key = (handler.name, handler.id);
-            seen.insert(key.to_owned());
-            if let std::collections::hash_map::Entry::Vacant(entry) = self.handlers.entry(key) {
-                let handle = (handler.set)(self.object.upcast_ref(), scope);
-                entry.insert(handle);
-            }
-        }
-        for key in self.handlers.keys() {
-            if !seen.contains(key) {
-                remove.push(key.to_owned());
+            if let Some(handle) = self.handlers.remove(&key) {
+                object.disconnect(handle);
             }
+            connected.insert(key, (handler.set)(object, scope));
         }
-        for key in remove {
-            let obj: &Object = self.object.upcast_ref();
-            obj.disconnect(self.handlers.remove(&key).unwrap());
+        for (_, handle) in self.handlers.drain() {
+            object.disconnect(handle);
         }
+        self.handlers = connected;
     }
 
     pub(crate) fn unmount(self) {
@@ -496,3 +1520,219 @@ impl<Model: 'static + Component> GtkState<Model> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys<'a>(children: &[&'a str]) -> Vec<Option<&'a str>> {
+        children.iter().copied().map(Some).collect()
+    }
+
+    // Simulate placing the children `new`, which replace `old`, in a container
+    // holding `before`, then `old`, then `after`, the way `patch_children`
+    // does in a container which can reorder its children. Children are told
+    // apart by name, as if they were keyed. Returns the container's children.
+    fn place(before: &[&str], old: &[&str], new: &[&str], after: &[&str]) -> Vec<String> {
+        let mut container: Vec<&str> = before.to_vec();
+        container.extend(old.iter().filter(|child| new.contains(child)));
+        container.extend(after);
+        container.extend(new.iter().filter(|child| !old.contains(child)));
+        let moves = placement_moves(&match_children(&keys(old), &keys(new)));
+        let mut anchor = before.last().copied();
+        for (child, moving) in new.iter().zip(moves) {
+            if moving {
+                container.retain(|other| other != child);
+                let position = match anchor {
+                    Some(anchor) => {
+                        container.iter().position(|&other| other == anchor).unwrap() + 1
+                    }
+                    None => 0,
+                };
+                container.insert(position, child);
+            }
+            anchor = Some(child);
+        }
+        container.into_iter().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn longest_increasing_subsequence_of_nothing() {
+        assert!(longest_increasing_subsequence(&[]).is_empty());
+    }
+
+    #[test]
+    fn longest_increasing_subsequence_in_order() {
+        assert_eq!(
+            longest_increasing_subsequence(&[0, 1, 2, 3]),
+            [0, 1, 2, 3].iter().copied().collect()
+        );
+    }
+
+    #[test]
+    fn longest_increasing_subsequence_reversed() {
+        assert_eq!(longest_increasing_subsequence(&[3, 2, 1, 0]).len(), 1);
+    }
+
+    #[test]
+    fn longest_increasing_subsequence_mixed() {
+        assert_eq!(
+            longest_increasing_subsequence(&[4, 0, 5, 1, 2, 6, 3]),
+            [0, 1, 2, 3].iter().copied().collect()
+        );
+        assert_eq!(
+            longest_increasing_subsequence(&[1, 0, 2]),
+            [0, 2].iter().copied().collect()
+        );
+    }
+
+    #[test]
+    fn match_keyed_children() {
+        assert_eq!(
+            match_children(&keys(&["a", "b", "c"]), &keys(&["c", "a", "d"])),
+            vec![Some(2), Some(0), None]
+        );
+    }
+
+    #[test]
+    fn match_unkeyed_children_in_order() {
+        assert_eq!(
+            match_children(&[None, Some("a"), None], &[Some("a"), None, None, None]),
+            vec![Some(1), Some(0), Some(2), None]
+        );
+    }
+
+    #[test]
+    fn keyed_move() {
+        assert_eq!(
+            place(&[], &["a", "b", "c", "d"], &["d", "a", "b", "c"], &[]),
+            vec!["d", "a", "b", "c"]
+        );
+        assert_eq!(
+            place(&["x"], &["a", "b", "c"], &["c", "b", "a"], &["y"]),
+            vec!["x", "c", "b", "a", "y"]
+        );
+    }
+
+    #[test]
+    fn keyed_insert() {
+        assert_eq!(
+            place(&[], &["a", "c"], &["a", "b", "c"], &[]),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(
+            place(&["x"], &["a", "c"], &["new", "a", "c"], &["y"]),
+            vec!["x", "new", "a", "c", "y"]
+        );
+    }
+
+    #[test]
+    fn keyed_delete() {
+        assert_eq!(
+            place(&["x"], &["a", "b", "c"], &["a", "c"], &["y"]),
+            vec!["x", "a", "c", "y"]
+        );
+    }
+
+    #[test]
+    fn keyed_move_insert_and_delete() {
+        assert_eq!(
+            place(&["x"], &["a", "b", "c", "d"], &["d", "e", "b", "a"], &["y"]),
+            vec!["x", "d", "e", "b", "a", "y"]
+        );
+    }
+
+    #[test]
+    fn replaced_root_stays_between_its_siblings() {
+        assert_eq!(
+            place(&["left"], &["old root"], &["new root"], &["right"]),
+            vec!["left", "new root", "right"]
+        );
+        assert_eq!(
+            place(&[], &["old root"], &["new root"], &["right"]),
+            vec!["new root", "right"]
+        );
+    }
+
+    #[test]
+    fn fragment_gains_a_child_before_its_siblings() {
+        // Box [Comp -> [X], Y] rendering Comp -> [X, Z].
+        assert_eq!(place(&[], &["X"], &["X", "Z"], &["Y"]), vec!["X", "Z", "Y"]);
+        assert_eq!(
+            place(&["W"], &["X"], &["Z", "X"], &["Y"]),
+            vec!["W", "Z", "X", "Y"]
+        );
+    }
+
+    #[test]
+    fn empty_component_gains_a_widget_between_its_siblings() {
+        assert_eq!(
+            place(&["left"], &[], &["widget"], &["right"]),
+            vec!["left", "widget", "right"]
+        );
+    }
+
+    // Rebuild the root of a component sitting in `old`'s place in `parent`,
+    // as a Button, and return the Button.
+    fn rebuild_as_button(parent: &Object, old: &Widget, child_props: &[VProperty]) -> Widget {
+        let (sender, _) = futures::channel::mpsc::unbounded();
+        let scope = Scope::<()>::new("test", sender);
+        let state: State<()> = State::Gtk(GtkState {
+            object: old.clone().upcast(),
+            key: None,
+            properties: Vec::new(),
+            child_props: child_props.to_vec(),
+            handlers: HashMap::new(),
+            children: Vec::new(),
+        });
+        let spec = placeholder(gtk::Button::static_type(), &[]);
+        let state = state.replace_root(&spec, Some(parent), &Anchor::Start, child_props, &scope);
+        state.widgets().remove(0)
+    }
+
+    // This needs GTK and a display, so it only runs with `--ignored`.
+    #[test]
+    #[ignore]
+    fn rebuilt_root_keeps_its_slot() {
+        gtk::init().expect("unable to initialise GTK");
+
+        let paned = Paned::new(gtk::Orientation::Horizontal);
+        let first = gtk::Label::new(None);
+        let second = gtk::Label::new(None);
+        paned.pack1(&first, false, true);
+        paned.pack2(&second, true, true);
+        let rebuilt = rebuild_as_button(paned.upcast_ref(), second.upcast_ref(), &[]);
+        assert_eq!(paned.get_child1(), Some(first.upcast()));
+        assert_eq!(paned.get_child2(), Some(rebuilt));
+
+        let window = Window::new(gtk::WindowType::Toplevel);
+        let main = gtk::Label::new(None);
+        let title = gtk::Label::new(None);
+        window.add(&main);
+        window.set_titlebar(Some(&title));
+        let rebuilt = rebuild_as_button(window.upcast_ref(), title.upcast_ref(), &[]);
+        assert_eq!(window.get_child(), Some(main.upcast()));
+        assert_eq!(window.get_titlebar(), Some(rebuilt));
+
+        let stack = Stack::new();
+        let pages: Vec<gtk::Label> = (0..3).map(|_| gtk::Label::new(None)).collect();
+        for (index, page) in pages.iter().enumerate() {
+            stack.add_named(page, &index.to_string());
+        }
+        let rebuilt = rebuild_as_button(stack.upcast_ref(), pages[1].upcast_ref(), &[]);
+        assert_eq!(stack.get_child_position(&rebuilt), 1);
+    }
+
+    #[test]
+    fn new_children_always_move() {
+        assert_eq!(placement_moves(&[None]), vec![true]);
+        assert_eq!(
+            placement_moves(&[Some(0), None, Some(1)]),
+            vec![false, true, false]
+        );
+        assert_eq!(
+            placement_moves(&[Some(1), Some(0), None]),
+            vec![true, false, true]
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use glib::Object;
+
+use std::any::Any;
+use std::rc::Rc;
+
+use super::{Anchor, State};
+use crate::component::Component;
+use crate::scope::Scope;
+use crate::vnode::VMemo;
+
+/// The state of a memoised node, which remembers the dependencies it was last
+/// patched with.
+pub(crate) struct MemoState<Model: Component> {
+    deps: Rc<dyn Any>,
+    pub(super) inner: Box<State<Model>>,
+}
+
+impl<Model: 'static + Component> MemoState<Model> {
+    pub(crate) fn build(
+        vmemo: &VMemo<Model>,
+        parent: Option<&Object>,
+        scope: &Scope<Model>,
+    ) -> Self {
+        MemoState {
+            deps: vmemo.deps.clone(),
+            inner: Box::new(State::build(&vmemo.node, parent, scope)),
+        }
+    }
+
+    pub(crate) fn patch(
+        &mut self,
+        vmemo: &VMemo<Model>,
+        parent: Option<&Object>,
+        anchor: &Anchor,
+        scope: &Scope<Model>,
+    ) -> bool {
+        // If the dependencies haven't changed, neither has anything under
+        // here, so we leave it alone.
+        if vmemo.unchanged(&*self.deps) {
+            return true;
+        }
+        if !self.inner.patch_root(&vmemo.node, parent, anchor, scope) {
+            return false;
+        }
+        self.deps = vmemo.deps.clone();
+        true
+    }
+}
@@ -1,19 +1,28 @@
-use glib::{Cast, Object};
+use glib::{Cast, Object, ObjectExt};
 use gtk::{self, Widget};
 
 use crate::component::Component;
 use crate::scope::Scope;
-use crate::vnode::VNode;
+use crate::vnode::{VNode, VProperty};
 
 mod component_state;
-pub(crate) use component_state::ComponentState;
+pub(crate) use component_state::{Anchor, ComponentState, Roots};
+
+mod fragment_state;
+use fragment_state::FragmentState;
 
 mod gtk_state;
-use gtk_state::GtkState;
+pub(crate) use gtk_state::longest_increasing_subsequence;
+use gtk_state::{place_root, remove_child, GtkState, Slot};
+
+mod memo_state;
+use memo_state::MemoState;
 
 pub(crate) enum State<Model: Component> {
     Gtk(GtkState<Model>),
     Component(ComponentState<Model>),
+    Fragment(FragmentState<Model>),
+    Memo(MemoState<Model>),
 }
 
 impl<Model: 'static + Component> State<Model> {
@@ -26,9 +35,14 @@ impl<Model: 'static + Component> State<Model> {
         match vnode {
             VNode::Object(object) => State::Gtk(GtkState::build(object, parent, scope)),
             VNode::Component(vcomp) => {
-                let comp = (vcomp.constructor)(&vcomp.props, parent, &vcomp.child_props, scope);
+                let mut comp = (vcomp.constructor)(&vcomp.props, parent, &vcomp.child_props, scope);
+                comp.key = vcomp.key.clone();
                 State::Component(comp)
             }
+            VNode::Fragment(_) | VNode::Empty => {
+                State::Fragment(FragmentState::build(vnode, parent, scope))
+            }
+            VNode::Memo(vmemo) => State::Memo(MemoState::build(vmemo, parent, scope)),
         }
     }
 
@@ -40,10 +54,9 @@ impl<Model: 'static + Component> State<Model> {
     ) -> Self {
         match vnode {
             VNode::Object(object) => State::Gtk(GtkState::build_root(object, parent, scope)),
-            VNode::Component(_vcomp) => {
-                // let comp = (vcomp.constructor)(&vcomp.props, parent, &vcomp.child_props, scope);
-                // State::Component(comp)
-                unimplemented!()
+            // Components, fragments and memos are built in full.
+            VNode::Component(_) | VNode::Fragment(_) | VNode::Empty | VNode::Memo(_) => {
+                State::build(vnode, parent, scope)
             }
         }
     }
@@ -54,7 +67,8 @@ impl<Model: 'static + Component> State<Model> {
                 State::Gtk(gtk_state) => gtk_state.build_children(vobject, scope),
                 _ => unimplemented!(),
             },
-            _ => unimplemented!(),
+            // Components, fragments and memos are built in full by `build_root()`.
+            VNode::Component(_) | VNode::Fragment(_) | VNode::Empty | VNode::Memo(_) => {}
         }
     }
 
@@ -67,15 +81,42 @@ impl<Model: 'static + Component> State<Model> {
         vnode: &VNode<Model>,
         parent: Option<&Object>,
         scope: &Scope<Model>,
+    ) -> bool {
+        self.patch_root(vnode, parent, &Anchor::Start, scope)
+    }
+
+    /// Patch the root state of a component in place with a `VItem` spec.
+    ///
+    /// If the root is a fragment, any objects it adds to the parent are placed
+    /// according to `anchor`.
+    ///
+    /// Returns true if patching succeeded, or false if a rebuild is required.
+    #[must_use]
+    pub(crate) fn patch_root(
+        &mut self,
+        vnode: &VNode<Model>,
+        parent: Option<&Object>,
+        anchor: &Anchor,
+        scope: &Scope<Model>,
     ) -> bool {
         match vnode {
             VNode::Object(object) => match self {
-                State::Gtk(state) => state.patch(object, parent, scope),
-                State::Component(_) => false,
+                State::Gtk(state) if state.object.get_type() == object.object_type => {
+                    state.patch(object, parent, scope)
+                }
+                _ => false,
             },
             VNode::Component(vcomp) => match self {
                 State::Component(state) => state.patch(vcomp, parent, scope),
-                State::Gtk(_) => false,
+                _ => false,
+            },
+            VNode::Fragment(_) | VNode::Empty => match self {
+                State::Fragment(state) => state.patch(vnode, parent, anchor, scope),
+                _ => false,
+            },
+            VNode::Memo(vmemo) => match self {
+                State::Memo(state) => state.patch(vmemo, parent, anchor, scope),
+                _ => false,
             },
         }
     }
@@ -84,22 +125,102 @@ impl<Model: 'static + Component> State<Model> {
         match self {
             State::Gtk(state) => state.unmount(),
             State::Component(state) => state.unmount(),
+            State::Fragment(state) => state.unmount(),
+            State::Memo(state) => state.inner.unmount(),
+        }
+    }
+
+    /// Get the key this state was built with, if any.
+    pub(crate) fn key(&self) -> Option<&str> {
+        match self {
+            State::Gtk(state) => state.key.as_deref(),
+            State::Component(state) => state.key.as_deref(),
+            State::Fragment(_) => None,
+            State::Memo(state) => state.inner.key(),
+        }
+    }
+
+    /// Get the Glib `Object`s represented by this state, in order.
+    pub(crate) fn objects(&self) -> Vec<Object> {
+        match self {
+            State::Gtk(state) => vec![state.object.clone()],
+            State::Component(state) => state.objects(),
+            State::Fragment(state) => state.objects(),
+            State::Memo(state) => state.inner.objects(),
         }
     }
 
-    /// Get the Glib `Object` represented by this state.
-    pub(crate) fn object(&self) -> &Object {
+    /// Get the first Glib `Object` represented by this state, if it has any.
+    pub(crate) fn object(&self) -> Option<Object> {
+        self.objects().into_iter().next()
+    }
+
+    /// Get the Gtk `Widget`s represented by this state, in order.
+    pub(crate) fn widgets(&self) -> Vec<Widget> {
+        self.objects()
+            .into_iter()
+            .filter_map(|object| object.downcast::<Widget>().ok())
+            .collect()
+    }
+
+    /// Tell a component where its parent has placed it, so that it can place
+    /// any objects it adds itself.
+    pub(crate) fn set_anchor(&self, anchor: &Anchor) {
         match self {
-            State::Gtk(state) => &state.object,
-            State::Component(state) => &state.object,
+            State::Component(state) => state.set_anchor(anchor),
+            State::Memo(state) => state.inner.set_anchor(anchor),
+            _ => {}
         }
     }
 
-    /// Get the Gtk `Widget` represented by this state, if it has a `Widget`.
-    pub(crate) fn widget(&self) -> Option<&Widget> {
+    /// An anchor for placing a sibling directly after this state's objects,
+    /// if there's anything to place it after.
+    pub(crate) fn anchor_after(&self) -> Option<Anchor> {
         match self {
-            State::Gtk(state) => state.object.downcast_ref::<Widget>(),
-            State::Component(state) => state.object.downcast_ref::<Widget>(),
+            State::Component(state) => Some(state.anchor_after()),
+            State::Memo(state) => state.inner.anchor_after(),
+            _ => self
+                .widgets()
+                .pop()
+                .map(|widget| Anchor::After(widget.upcast::<Object>().downgrade())),
+        }
+    }
+
+    /// Replace the root state of a component with a new state built from
+    /// `vnode`, after patching it has failed.
+    ///
+    /// The new root objects take the place of the old ones in the parent: the
+    /// same slot, if the parent has slots, or else the same position, according
+    /// to `anchor`. They're placed with `child_props`, which should include
+    /// those of every component this one is the root node of.
+    pub(crate) fn replace_root(
+        self,
+        vnode: &VNode<Model>,
+        parent: Option<&Object>,
+        anchor: &Anchor,
+        child_props: &[VProperty],
+        scope: &Scope<Model>,
+    ) -> Self {
+        match parent {
+            Some(parent) => {
+                // Note which slot the old root is in before taking it out,
+                // so the new one can go in the same place.
+                let slot = match self.widgets().as_slice() {
+                    [widget] => Some(Slot::of(parent, widget)),
+                    _ => None,
+                };
+                for object in self.objects() {
+                    remove_child(parent, &object);
+                }
+                self.unmount();
+                let state = State::build(vnode, Some(parent), scope);
+                place_root(parent, anchor, slot, &state, child_props);
+                state
+            }
+            None => {
+                self.unmount();
+                State::build(vnode, None, scope)
+            }
         }
     }
 }
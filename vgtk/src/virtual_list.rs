@@ -0,0 +1,394 @@
+use gio::{ListStore, ListStoreExt};
+use glib::{Cast, Object, ObjectExt, StaticType, WeakRef};
+use gtk::{FlowBox, FlowBoxExt, ListBox, ListBoxExt, Widget};
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::component::{current_parent_object, Component, UpdateAction};
+use crate::scope::Scope;
+use crate::types::ListItem;
+use crate::vdom::State;
+use crate::vnode::{PropTransform, VComponent, VNode};
+
+/// The rows a [`VirtualList`][VirtualList] has built, which belong to the
+/// component the list was rendered by.
+///
+/// [VirtualList]: struct.VirtualList.html
+trait Rows<T> {
+    /// Build rows from a new render function from now on.
+    fn set_render(&self, render: &Rc<dyn Any>);
+    /// Build the row for a model item which stands for `value`.
+    fn build(&self, item: &Object, value: &T) -> Widget;
+    /// Unmount the row for a model item which has gone away.
+    fn remove(&self, item: &Object);
+    /// Unmount every row.
+    fn clear(&self);
+}
+
+struct RowStates<T, Model: Component> {
+    scope: Scope<Model>,
+    render: RefCell<Rc<dyn Fn(&T) -> VNode<Model>>>,
+    states: RefCell<HashMap<Object, State<Model>>>,
+}
+
+fn render_fn<T: 'static, Model: 'static + Component>(
+    render: &Rc<dyn Any>,
+) -> Rc<dyn Fn(&T) -> VNode<Model>> {
+    render
+        .downcast_ref::<Rc<dyn Fn(&T) -> VNode<Model>>>()
+        .expect("VirtualList's row function changed type")
+        .clone()
+}
+
+// Rows are built with the scope of the component rendering the list, so this
+// has to be called from inside the list's own context.
+fn bind_rows<T: 'static, Model: 'static + Component>(render: &Rc<dyn Any>) -> Rc<dyn Rows<T>> {
+    Rc::new(RowStates {
+        scope: Scope::<Model>::current_parent(),
+        render: RefCell::new(render_fn::<T, Model>(render)),
+        states: Default::default(),
+    })
+}
+
+impl<T: 'static, Model: 'static + Component> Rows<T> for RowStates<T, Model> {
+    fn set_render(&self, render: &Rc<dyn Any>) {
+        *self.render.borrow_mut() = render_fn(render);
+    }
+
+    fn build(&self, item: &Object, value: &T) -> Widget {
+        let render = self.render.borrow().clone();
+        let state = State::build(&render(value), None, &self.scope);
+        let mut widgets = state.widgets();
+        if widgets.len() != 1 {
+            panic!(
+                "A VirtualList's row must be a single Widget, but {} were found.",
+                widgets.len()
+            );
+        }
+        let replaced = self.states.borrow_mut().insert(item.clone(), state);
+        if let Some(replaced) = replaced {
+            replaced.unmount();
+        }
+        widgets.remove(0)
+    }
+
+    fn remove(&self, item: &Object) {
+        let state = self.states.borrow_mut().remove(item);
+        if let Some(state) = state {
+            state.unmount();
+        }
+    }
+
+    fn clear(&self) {
+        let states: Vec<_> = self.states.borrow_mut().drain().collect();
+        for (_, state) in states {
+            state.unmount();
+        }
+    }
+}
+
+/// The function a [`VirtualList`][VirtualList] renders each of its rows with.
+///
+/// You don't need to build one yourself. Give the list's `row` property a
+/// closure which takes a reference to an item and returns a [`VNode`][VNode]
+/// for your own component, and it becomes a `RowView` in the same way a
+/// closure becomes a [`Callback`][Callback].
+///
+/// [VirtualList]: struct.VirtualList.html
+/// [VNode]: enum.VNode.html
+/// [Callback]: struct.Callback.html
+#[allow(clippy::type_complexity)]
+pub struct RowView<T>(Option<(Rc<dyn Any>, fn(&Rc<dyn Any>) -> Rc<dyn Rows<T>>)>);
+
+impl<T> Default for RowView<T> {
+    fn default() -> Self {
+        RowView(None)
+    }
+}
+
+impl<T> Clone for RowView<T> {
+    fn clone(&self) -> Self {
+        RowView(self.0.clone())
+    }
+}
+
+impl<Model, F, T> PropTransform<Model, F, RowView<T>> for VComponent<Model>
+where
+    Model: Component + 'static,
+    F: Fn(&T) -> VNode<Model> + 'static,
+    T: 'static,
+{
+    fn transform(&self, from: F) -> RowView<T> {
+        let render: Rc<dyn Fn(&T) -> VNode<Model>> = Rc::new(from);
+        RowView(Some((Rc::new(render), bind_rows::<T, Model>)))
+    }
+}
+
+/// The properties of a [`VirtualList`][VirtualList].
+///
+/// [VirtualList]: struct.VirtualList.html
+pub struct VirtualListProperties<T> {
+    /// The items to show a row for.
+    pub items: Vec<T>,
+    /// The function to render each row with.
+    pub row: RowView<T>,
+}
+
+impl<T> Default for VirtualListProperties<T> {
+    fn default() -> Self {
+        VirtualListProperties {
+            items: Vec::new(),
+            row: Default::default(),
+        }
+    }
+}
+
+impl<T: Clone> Clone for VirtualListProperties<T> {
+    fn clone(&self) -> Self {
+        VirtualListProperties {
+            items: self.items.clone(),
+            row: self.row.clone(),
+        }
+    }
+}
+
+/// A list of rows built on demand from a list of items, for lists too long to
+/// render as [`VNode`][VNode] children.
+///
+/// A `VirtualList` goes inside a [`ListBox`][ListBox] or a [`FlowBox`][FlowBox],
+/// as its only child, and binds it to a [`gio::ListModel`][ListModel] standing
+/// in for its `items`. The container asks for a row as each item is added to
+/// the model, and gets it from the `row` function, which renders an item as a
+/// tree of widgets belonging to the component rendering the list, so their
+/// signal handlers send messages to that component.
+///
+/// When `items` changes, the items are matched up with the previous list by
+/// their [`ListItem::key()`][key], and only the items which were added, removed,
+/// moved or changed are replaced in the model, which tells the container
+/// through its `items_changed` signal. An item's row is only rendered again
+/// when the item changes, so the row should depend on nothing but its item.
+///
+/// ```rust,no_run
+/// # use vgtk::{gtk, types::ListItem, Component, VNode, VirtualList};
+/// # use vgtk::lib::gtk::*;
+/// #[derive(Clone, PartialEq)]
+/// struct Song {
+///     id: u32,
+///     title: String,
+/// }
+///
+/// impl ListItem for Song {
+///     type Key = u32;
+///
+///     fn key(&self) -> u32 {
+///         self.id
+///     }
+/// }
+///
+/// # #[derive(Clone, Debug)] enum Message { Play(u32) }
+/// # #[derive(Default)] struct Playlist { songs: Vec<Song> }
+/// # impl Component for Playlist { type Message = Message; type Properties = ();
+/// # fn view(&self) -> VNode<Self> {
+/// gtk! {
+///     <ScrolledWindow>
+///         <ListBox selection_mode=SelectionMode::None>
+///             <@VirtualList<Song> items=self.songs.clone() row = |song: &Song| {
+///                 let id = song.id;
+///                 gtk! {
+///                     <Button label=song.title.as_str() on clicked = |_| Message::Play(id) />
+///                 }
+///             } />
+///         </ListBox>
+///     </ScrolledWindow>
+/// }
+/// # }}
+/// ```
+///
+/// [VNode]: enum.VNode.html
+/// [ListBox]: ../gtk/struct.ListBox.html
+/// [FlowBox]: ../gtk/struct.FlowBox.html
+/// [ListModel]: ../gio/struct.ListModel.html
+/// [key]: types/trait.ListItem.html#tymethod.key
+pub struct VirtualList<T: ListItem> {
+    items: Vec<T>,
+    row: RowView<T>,
+    model: ListStore,
+    // The model's items, which stand for `items` one for one.
+    objects: Vec<Object>,
+    values: Rc<RefCell<HashMap<Object, T>>>,
+    rows: Option<Rc<dyn Rows<T>>>,
+    parent: Option<WeakRef<Object>>,
+}
+
+// Nothing is ever pinned inside a component.
+impl<T: ListItem> Unpin for VirtualList<T> {}
+
+impl<T: ListItem> Default for VirtualList<T> {
+    fn default() -> Self {
+        VirtualList {
+            items: Vec::new(),
+            row: Default::default(),
+            model: ListStore::new(Object::static_type()),
+            objects: Vec::new(),
+            values: Default::default(),
+            rows: None,
+            parent: None,
+        }
+    }
+}
+
+impl<T: ListItem> VirtualList<T> {
+    fn insert(&mut self, position: usize, value: T) {
+        let item = Object::new(Object::static_type(), &[]).expect("unable to create list item");
+        // The container builds the row as soon as the item is in the model.
+        self.values.borrow_mut().insert(item.clone(), value.clone());
+        self.items.insert(position, value);
+        self.objects.insert(position, item.clone());
+        self.model.insert(position as u32, &item);
+    }
+
+    fn replace(&mut self, position: usize, value: T) {
+        let item = Object::new(Object::static_type(), &[]).expect("unable to create list item");
+        self.values.borrow_mut().insert(item.clone(), value.clone());
+        self.items[position] = value;
+        let old = std::mem::replace(&mut self.objects[position], item.clone());
+        self.model.splice(position as u32, 1, &[item]);
+        self.forget(&old);
+    }
+
+    fn remove(&mut self, position: usize, count: usize) {
+        self.items.drain(position..position + count);
+        let removed: Vec<Object> = self.objects.drain(position..position + count).collect();
+        self.model.splice(position as u32, count as u32, &[]);
+        for item in &removed {
+            self.forget(item);
+        }
+    }
+
+    fn forget(&self, item: &Object) {
+        self.values.borrow_mut().remove(item);
+        if let Some(rows) = &self.rows {
+            rows.remove(item);
+        }
+    }
+
+    fn set_items(&mut self, items: Vec<T>) {
+        let mut wanted = HashMap::with_capacity(items.len());
+        for (index, item) in items.iter().enumerate() {
+            if wanted.insert(item.key(), index).is_some() {
+                panic!("VirtualList items must have unique keys, but a key was found twice.");
+            }
+        }
+        for (position, item) in items.into_iter().enumerate() {
+            // Drop the items which have gone away, or which have already been
+            // placed further up, until we reach one which is wanted here or
+            // further down.
+            let gone = self.items[position..]
+                .iter()
+                .take_while(|current| match wanted.get(&current.key()) {
+                    Some(&index) => index < position,
+                    None => true,
+                })
+                .count();
+            if gone > 0 {
+                self.remove(position, gone);
+            }
+            match self.items.get(position) {
+                Some(current) if *current == item => {}
+                Some(current) if current.key() == item.key() => self.replace(position, item),
+                _ => self.insert(position, item),
+            }
+        }
+        let extra = self.items.len() - wanted.len();
+        if extra > 0 {
+            self.remove(wanted.len(), extra);
+        }
+    }
+
+    /// Bind the model to the list's parent, which builds the rows for the
+    /// items it already has.
+    ///
+    /// This is called from `create()`, which runs in the list's own context.
+    fn bind(&mut self) {
+        let (render, bind) = self
+            .row
+            .0
+            .clone()
+            .expect("VirtualList needs a row function to render its items with");
+        let parent = current_parent_object().expect("VirtualList has no parent to bind");
+        let rows = bind(&render);
+        let values = self.values.clone();
+        let build = {
+            let rows = rows.clone();
+            move |item: &Object| {
+                let value = values
+                    .borrow()
+                    .get(item)
+                    .cloned()
+                    .expect("VirtualList was asked for a row for an item it doesn't have");
+                rows.build(item, &value)
+            }
+        };
+        if let Some(list_box) = parent.downcast_ref::<ListBox>() {
+            list_box.bind_model(Some(&self.model), build);
+        } else if let Some(flow_box) = parent.downcast_ref::<FlowBox>() {
+            flow_box.bind_model(Some(&self.model), build);
+        } else {
+            panic!(
+                "A VirtualList's parent must be a ListBox or a FlowBox, but {} was found.",
+                parent.get_type()
+            );
+        }
+        self.rows = Some(rows);
+        self.parent = Some(parent.downgrade());
+    }
+}
+
+impl<T: ListItem> Component for VirtualList<T> {
+    type Message = ();
+    type Properties = VirtualListProperties<T>;
+
+    fn create(props: Self::Properties) -> Self {
+        let mut list = VirtualList {
+            row: props.row,
+            ..Default::default()
+        };
+        list.set_items(props.items);
+        list.bind();
+        list
+    }
+
+    fn change(&mut self, props: Self::Properties) -> UpdateAction<Self> {
+        if let (Some(rows), Some((render, _))) = (&self.rows, &props.row.0) {
+            rows.set_render(render);
+        }
+        self.row = props.row;
+        self.set_items(props.items);
+        UpdateAction::None
+    }
+
+    fn unmounted(&mut self) {
+        let parent = self.parent.take().and_then(|parent| parent.upgrade());
+        if let Some(list_box) = parent
+            .as_ref()
+            .and_then(|parent| parent.downcast_ref::<ListBox>())
+        {
+            list_box.bind_model(None::<&ListStore>, |_| unreachable!());
+        } else if let Some(flow_box) = parent
+            .as_ref()
+            .and_then(|parent| parent.downcast_ref::<FlowBox>())
+        {
+            flow_box.bind_model(None::<&ListStore>, |_| unreachable!());
+        }
+        if let Some(rows) = self.rows.take() {
+            rows.clear();
+        }
+    }
+
+    fn view(&self) -> VNode<Self> {
+        VNode::Empty
+    }
+}
@@ -61,6 +61,7 @@ pub struct VComponent<Model: Component> {
     pub props: AnyProps,
     pub constructor: Box<Constructor<Model>>,
     pub child_props: Vec<VProperty>,
+    pub key: Option<String>,
 }
 
 impl<Model: 'static + Component> VComponent<Model> {
@@ -72,6 +73,7 @@ impl<Model: 'static + Component> VComponent<Model> {
             props: AnyProps::null(),
             constructor,
             child_props: Vec::new(),
+            key: None,
         }
     }
 
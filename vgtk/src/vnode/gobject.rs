@@ -12,6 +12,7 @@ pub struct VObject<Model: Component> {
     pub child_props: Vec<VProperty>,
     pub handlers: Vec<VHandler<Model>>,
     pub children: Vec<VNode<Model>>,
+    pub key: Option<String>,
 }
 
 impl<Model: Component> VObject<Model> {
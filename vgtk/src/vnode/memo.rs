@@ -0,0 +1,37 @@
+use std::any::Any;
+use std::rc::Rc;
+
+use crate::component::Component;
+use crate::vnode::VNode;
+
+/// A node which is only patched when its dependencies have changed.
+///
+/// Use the `<memo deps=...>` element in the [`gtk!`][gtk!] macro to construct this.
+///
+/// [gtk!]: macro.gtk.html
+pub struct VMemo<Model: Component> {
+    pub deps: Rc<dyn Any>,
+    pub equal: fn(&dyn Any, &dyn Any) -> bool,
+    pub node: Box<VNode<Model>>,
+}
+
+impl<Model: Component> VMemo<Model> {
+    pub fn new<Deps: PartialEq + 'static>(deps: Deps, node: VNode<Model>) -> Self {
+        fn equal<Deps: PartialEq + 'static>(left: &dyn Any, right: &dyn Any) -> bool {
+            match (left.downcast_ref::<Deps>(), right.downcast_ref::<Deps>()) {
+                (Some(left), Some(right)) => left == right,
+                _ => false,
+            }
+        }
+        VMemo {
+            deps: Rc::new(deps),
+            equal: equal::<Deps>,
+            node: Box::new(node),
+        }
+    }
+
+    /// Test whether these dependencies are equal to an earlier set.
+    pub(crate) fn unchanged(&self, deps: &dyn Any) -> bool {
+        (self.equal)(&*self.deps, deps)
+    }
+}
@@ -1,24 +1,59 @@
+use std::iter::FromIterator;
+
 use crate::Component;
 
 pub(crate) mod component;
 mod gobject;
 mod handler;
+mod memo;
 mod property;
 
 pub use component::{PropTransform, VComponent};
 pub use gobject::VObject;
 pub use handler::VHandler;
+pub use memo::VMemo;
 pub use property::VProperty;
 
-/// A node in the virtual component tree representing a [`Component`][Component] or a Gtk widget.
+/// A node in the virtual component tree representing a [`Component`][Component], a Gtk widget,
+/// or a list of either.
+///
+/// Don't attempt to construct objects or components directly. Use the [`gtk!`][gtk!] macro
+/// instead.
+///
+/// A fragment lets a [`Component`][Component] render zero or more nodes where it would
+/// otherwise have to render exactly one: when a subcomponent renders a fragment, the nodes
+/// inside it are added to the subcomponent's parent as if they'd been its children. You can
+/// also collect an iterator of `VNode`s into a fragment.
+///
+/// # Examples
 ///
-/// Don't attempt to construct these directly. Use the [`gtk!`][gtk!] macro instead.
+/// ```rust,no_run
+/// # use vgtk::{gtk, VNode, Component};
+/// # use vgtk::lib::gtk::{Button, ButtonExt};
+/// # #[derive(Default)] struct Buttons { labels: Vec<String> }
+/// # impl Component for Buttons { type Message = (); type Properties = ();
+/// fn view(&self) -> VNode<Self> {
+///     self.labels
+///         .iter()
+///         .map(|label| gtk! { <Button label=label.clone() /> })
+///         .collect()
+/// }
+/// # }
+/// ```
 ///
 /// [gtk!]: macro.gtk.html
 /// [Component]: trait.Component.html
 pub enum VNode<Model: Component> {
+    /// A Gtk object.
     Object(VObject<Model>),
+    /// A subcomponent.
     Component(VComponent<Model>),
+    /// A list of nodes, rendered in place of this one.
+    Fragment(Vec<VNode<Model>>),
+    /// Nothing at all. This is the same as an empty `Fragment`.
+    Empty,
+    /// A node which is only patched when its dependencies change.
+    Memo(VMemo<Model>),
 }
 
 impl<Model: Component> VNode<Model> {
@@ -26,6 +61,17 @@ impl<Model: Component> VNode<Model> {
         match self {
             VNode::Object(object) => &object.child_props,
             VNode::Component(comp) => &comp.child_props,
+            VNode::Fragment(_) | VNode::Empty => &[],
+            VNode::Memo(memo) => memo.node.get_child_props(),
+        }
+    }
+
+    pub(crate) fn get_key(&self) -> Option<&str> {
+        match self {
+            VNode::Object(object) => object.key.as_deref(),
+            VNode::Component(comp) => comp.key.as_deref(),
+            VNode::Fragment(_) | VNode::Empty => None,
+            VNode::Memo(memo) => memo.node.get_key(),
         }
     }
 
@@ -38,6 +84,28 @@ impl<Model: Component> VNode<Model> {
         }
         None
     }
+
+    // Replace any fragments in a list of nodes with the nodes they contain.
+    pub(crate) fn flatten<'a>(nodes: impl IntoIterator<Item = &'a Self>) -> Vec<&'a Self>
+    where
+        Model: 'a,
+    {
+        let mut flat = Vec::new();
+        for node in nodes {
+            match node {
+                VNode::Fragment(children) => flat.extend(VNode::flatten(children)),
+                VNode::Empty => {}
+                node => flat.push(node),
+            }
+        }
+        flat
+    }
+}
+
+impl<Model: Component> FromIterator<VNode<Model>> for VNode<Model> {
+    fn from_iter<I: IntoIterator<Item = VNode<Model>>>(iter: I) -> Self {
+        VNode::Fragment(iter.into_iter().collect())
+    }
 }
 
 /// An iterator over zero or one [`VNode`][VNode]s.
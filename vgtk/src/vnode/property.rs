@@ -1,6 +1,37 @@
 use glib::Object;
 
+use crate::properties::is_live;
+
+use std::any::Any;
+use std::rc::Rc;
+
+#[derive(Clone)]
 pub struct VProperty {
     pub name: &'static str,
-    pub set: Box<dyn Fn(&Object, Option<&Object>, bool) + 'static>,
+    /// The value to set, kept for comparing with the next render's value.
+    pub value: Rc<dyn Any>,
+    pub equal: fn(&dyn Any, &dyn Any) -> bool,
+    /// Whether to compare with the object's current value rather than the
+    /// previous render's.
+    pub live: bool,
+    pub set: Rc<dyn Fn(&Object, Option<&Object>, bool) + 'static>,
+}
+
+impl VProperty {
+    /// Apply this property to an object which had `previous` applied to it in
+    /// the last render, if it's changed since then.
+    pub(crate) fn patch(&self, object: &Object, parent: Option<&Object>, previous: Option<&Self>) {
+        if self.live || is_live(object, self.name) {
+            // The setter asks the object for its current value first.
+            (self.set)(object, parent, false);
+            return;
+        }
+        let unchanged = match previous {
+            Some(previous) => (self.equal)(self.value.as_ref(), previous.value.as_ref()),
+            None => false,
+        };
+        if !unchanged {
+            (self.set)(object, parent, true);
+        }
+    }
 }